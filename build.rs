@@ -0,0 +1,18 @@
+//! Records how the crate was built, for the host and build metadata in the results.
+
+use std::env;
+use std::process::Command;
+
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+
+    let rustc = env::var("RUSTC").unwrap_or("rustc".to_string());
+    let version = Command::new(rustc).arg("--version").output().ok()
+        .and_then(|o| String::from_utf8(o.stdout).ok())
+        .map(|v| v.trim().to_string())
+        .unwrap_or("unknown".to_string());
+
+    println!("cargo:rustc-env=WARGAME_RUSTC_VERSION={}", version);
+    println!("cargo:rustc-env=WARGAME_TARGET={}", env::var("TARGET").unwrap_or("unknown".to_string()));
+    println!("cargo:rustc-env=WARGAME_PROFILE={}", env::var("PROFILE").unwrap_or("unknown".to_string()));
+}
@@ -0,0 +1,251 @@
+//! Pinning worker threads to CPUs.
+//!
+//! Left alone, the workers migrate between CPUs as the scheduler pleases,
+//! which on NUMA machines makes results vary from run to run.
+//! Pinning is only supported on Linux, through `sched_setaffinity`.
+
+use std::fmt;
+
+use host::read_file;
+
+/// How workers are placed on CPUs.
+#[derive(Clone, PartialEq)]
+pub enum Affinity {
+    /// Worker `i` on the `i`th allowed CPU, in CPU number order, wrapping around.
+    RoundRobin,
+    /// Fill each core, then each package, before moving on to the next;
+    /// hyperthread siblings are used together.
+    Compact,
+    /// Spread over packages first, then over cores,
+    /// using a core's hyperthread siblings only once every core has a worker.
+    Scatter,
+    /// Worker `i` on the `i`th CPU of the list, wrapping around.
+    Cores(Vec<usize>),
+}
+
+/// Displays the affinity as it is given on the command line.
+impl fmt::Display for Affinity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Affinity::RoundRobin => write!(f, "round-robin"),
+            Affinity::Compact => write!(f, "compact"),
+            Affinity::Scatter => write!(f, "scatter"),
+            Affinity::Cores(ref cores) => {
+                let cores: Vec<String> = cores.iter().map(|c| c.to_string()).collect();
+                write!(f, "{}", cores.join(","))
+            }
+        }
+    }
+}
+
+/// The CPU each of `threads` workers should be pinned to.
+///
+/// Returns `None` where pinning is not supported, or no CPUs are allowed.
+pub fn plan(affinity: &Affinity, threads: usize) -> Option<Vec<usize>> {
+    let order = match *affinity {
+        Affinity::Cores(ref cores) => cores.clone(),
+        _ => {
+            let mut cpus: Vec<Cpu> = match allowed_cpus() {
+                Some(cpus) => cpus.into_iter().map(topology).collect(),
+                None => return None
+            };
+            match *affinity {
+                Affinity::Compact => cpus.sort_by_key(|c| (c.package, c.core_rank, c.sibling_rank)),
+                Affinity::Scatter => cpus.sort_by_key(|c| (c.sibling_rank, c.core_rank, c.package)),
+                _ => cpus.sort_by_key(|c| c.id)
+            }
+            cpus.into_iter().map(|c| c.id).collect()
+        }
+    };
+    if order.is_empty() {
+        return None;
+    }
+    Some((0..threads).map(|i| order[i % order.len()]).collect())
+}
+
+/// Pins the calling thread to `cpu`.
+///
+/// Returns the CPU when the thread's affinity reads back as exactly that CPU.
+#[cfg(target_os = "linux")]
+pub fn pin(cpu: usize) -> Option<usize> {
+    use std::mem;
+    use libc;
+
+    if cpu >= libc::CPU_SETSIZE as usize {
+        return None;
+    }
+    unsafe {
+        let mut set: libc::cpu_set_t = mem::zeroed();
+        libc::CPU_SET(cpu, &mut set);
+        if libc::sched_setaffinity(0, mem::size_of::<libc::cpu_set_t>(), &set) != 0 {
+            return None;
+        }
+
+        let mut actual: libc::cpu_set_t = mem::zeroed();
+        if libc::sched_getaffinity(0, mem::size_of::<libc::cpu_set_t>(), &mut actual) != 0 {
+            return None;
+        }
+        if libc::CPU_COUNT(&actual) == 1 && libc::CPU_ISSET(cpu, &actual) {
+            Some(cpu)
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn pin(_: usize) -> Option<usize> {
+    None
+}
+
+/// The number of CPUs this process may run on, and at least one.
+///
+/// On Linux this is the size of the affinity mask, so it respects `taskset` and cgroup cpusets;
+/// elsewhere it is the number of CPUs online.
+pub fn cpu_count() -> usize {
+    match allowed_cpus() {
+        Some(ref cpus) if !cpus.is_empty() => cpus.len(),
+        _ => online_cpus().unwrap_or(1)
+    }
+}
+
+/// The number of CPUs online, and at least one, whatever this process may run on.
+pub fn online_cpu_count() -> usize {
+    online_cpus().unwrap_or(1)
+}
+
+#[cfg(unix)]
+fn online_cpus() -> Option<usize> {
+    use libc;
+
+    let n = unsafe { libc::sysconf(libc::_SC_NPROCESSORS_ONLN) };
+    if n > 0 {
+        Some(n as usize)
+    } else {
+        None
+    }
+}
+
+#[cfg(not(unix))]
+fn online_cpus() -> Option<usize> {
+    None
+}
+
+/// A CPU, and where it sits.
+struct Cpu {
+    id: usize,
+    package: usize,
+    /// The core's position among the cores of its package.
+    core_rank: usize,
+    /// The CPU's position among the hyperthreads of its core.
+    sibling_rank: usize,
+}
+
+/// The CPUs this process may run on.
+#[cfg(target_os = "linux")]
+fn allowed_cpus() -> Option<Vec<usize>> {
+    use std::mem;
+    use libc;
+
+    unsafe {
+        let mut set: libc::cpu_set_t = mem::zeroed();
+        if libc::sched_getaffinity(0, mem::size_of::<libc::cpu_set_t>(), &mut set) != 0 {
+            return None;
+        }
+        Some((0..libc::CPU_SETSIZE as usize).filter(|&c| libc::CPU_ISSET(c, &set)).collect())
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+fn allowed_cpus() -> Option<Vec<usize>> {
+    None
+}
+
+/// Reads a CPU's place from sysfs; where that fails,
+/// the CPU is treated as a core of its own.
+fn topology(id: usize) -> Cpu {
+    let package = read_topology(id, "physical_package_id").unwrap_or(0);
+    let core_id = read_topology(id, "core_id").unwrap_or(id);
+
+    // ranks are positions in the sorted lists of ids,
+    // since core ids are not always contiguous
+    let mut cores = read_list(&format!("/sys/devices/system/cpu/cpu{}/topology/core_cpus_list", id))
+        .or_else(|| read_list(&format!("/sys/devices/system/cpu/cpu{}/topology/thread_siblings_list", id)))
+        .unwrap_or_else(|| vec![id]);
+    cores.sort();
+    let sibling_rank = cores.iter().position(|&c| c == id).unwrap_or(0);
+
+    let mut package_cores: Vec<usize> = read_list(&format!("/sys/devices/system/cpu/cpu{}/topology/package_cpus_list", id))
+        .or_else(|| read_list(&format!("/sys/devices/system/cpu/cpu{}/topology/core_siblings_list", id)))
+        .unwrap_or_else(|| vec![id])
+        .into_iter()
+        .map(|c| read_topology(c, "core_id").unwrap_or(c))
+        .collect();
+    package_cores.sort();
+    package_cores.dedup();
+    let core_rank = package_cores.iter().position(|&c| c == core_id).unwrap_or(0);
+
+    Cpu {id, package, core_rank, sibling_rank}
+}
+
+fn read_topology(id: usize, name: &str) -> Option<usize> {
+    read_file(&format!("/sys/devices/system/cpu/cpu{}/topology/{}", id, name))
+        .and_then(|s| s.trim().parse().ok())
+}
+
+/// Reads a CPU list file, such as `0-3,8-11`.
+fn read_list(path: &str) -> Option<Vec<usize>> {
+    read_file(path).and_then(|s| parse_cpu_list(s.trim()).ok())
+}
+
+/// Parses a list of CPUs and inclusive ranges, such as `0,2,4-7`.
+pub fn parse_cpu_list(list: &str) -> Result<Vec<usize>, String> {
+    let mut cpus = Vec::new();
+    for item in list.split(',') {
+        let bad = || format!("`{}` is not a CPU or a range of CPUs", item);
+        match item.find('-') {
+            Some(p) => {
+                let first: usize = item[..p].trim().parse().map_err(|_| bad())?;
+                let last: usize = item[p + 1..].trim().parse().map_err(|_| bad())?;
+                if first > last {
+                    return Err(bad());
+                }
+                cpus.extend(first..last + 1);
+            },
+            None => cpus.push(item.trim().parse().map_err(|_| bad())?)
+        }
+    }
+    Ok(cpus)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cpu_lists_take_ranges() {
+        assert_eq!(parse_cpu_list("0,2,4-7"), Ok(vec![0, 2, 4, 5, 6, 7]));
+        assert_eq!(parse_cpu_list(" 3 "), Ok(vec![3]));
+        assert!(parse_cpu_list("3-1").is_err());
+        assert!(parse_cpu_list("1,,2").is_err());
+        assert!(parse_cpu_list("a-b").is_err());
+    }
+
+    #[test]
+    fn listed_cores_wrap_around() {
+        assert_eq!(plan(&Affinity::Cores(vec![3, 5]), 5), Some(vec![3, 5, 3, 5, 3]));
+        assert_eq!(plan(&Affinity::Cores(Vec::new()), 2), None);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn every_policy_places_a_worker_on_each_allowed_cpu() {
+        let allowed = allowed_cpus().unwrap();
+        assert_eq!(plan(&Affinity::RoundRobin, allowed.len()), Some(allowed.clone()));
+        for affinity in [Affinity::Compact, Affinity::Scatter].iter() {
+            let mut cpus = plan(affinity, allowed.len()).unwrap();
+            cpus.sort();
+            assert_eq!(cpus, allowed);
+        }
+    }
+}
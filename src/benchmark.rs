@@ -1,65 +1,212 @@
-use time::precise_time_ns;
-
-use std::sync::mpsc::channel;
-use std::sync::mpsc::Sender;
-use std::sync::mpsc::Receiver;
+use std::fmt;
 
-use std::thread;
+use time::precise_time_ns;
 
 use std::io::{self, Write};
 
 use std::collections::HashMap;
 
-use rand;
+use workers::Workers;
+
+use wg::Rules;
+
+use affinity;
+use affinity::Affinity;
+
+use host::HostInfo;
+
+use interval;
+use interval::IntervalMethod;
 
-use wg;
+use report::{BenchmarkReport, SeriesSample};
 
 const MS:u64 = 1000000;
 const NS:u64 = 1000000000;
 
-pub fn benchmark(threads: usize, multiplier: f64) {
+/// 1/10 of a second
+pub const DISPLAY_FREQUENCY:u64 = NS/10;
 
-    // these are communication channels
-    let mut terminate_senders = Vec::<Sender<u32>>::new(); // ts_
-    let mut termination_receivers = Vec::<Receiver<u32>>::new(); // tr_
-    let mut completion_receivers = Vec::<Receiver<u32>>::new(); // c_
+/// 1/200 of a second
+pub const SAMPLE_FREQUENCY:u64 = NS/200;
 
-    // create threads, and store channel pipes in the respective vectors
-    create_threads(threads, &mut terminate_senders, &mut termination_receivers, &mut completion_receivers);
+/// 10 seconds
+pub const PRIME_TIME:u64 = 10000000000;
 
-    // 1/10 of a second
-    const DISPLAY_FREQUENCY:u64 = NS/10;
+/// 50 seconds
+pub const SAMPLE_TIME:u64 = 50000000000;
 
-    // 1/200 of a second
-    const SAMPLE_FREQUENCY:u64 = NS/200;
+/// 1 second
+pub const STEADY_WINDOW:u64 = NS;
 
-    // 10 seconds
-    let mut prime_time:u64 = 10000000000;
-    // 50 seconds
-    let mut sample_time:u64 = 50000000000;
+/// The most samples space is made for before the run; more grow the lists as they come.
+const PREALLOCATED_SAMPLES:u64 = 100000;
 
-    if multiplier != 1.00 {
-        prime_time = (prime_time as f64 * multiplier) as u64;
-        sample_time = (sample_time as f64 * multiplier) as u64;
+/// 1%
+pub const STEADY_COV:f64 = 0.01;
+
+/// How often adaptive sampling checks the confidence interval: at most once a second
+pub const PRECISION_CHECK:u64 = NS;
+
+/// How much the samples must grow between checks of the confidence interval: by a quarter
+///
+/// The bootstrap (`IntervalMethod::Bootstrap`) takes time quadratic in the sample count,
+/// in finding the autocorrelation time it sizes its blocks by (`interval::autocorrelation_time`);
+/// spaced geometrically, its checks cost a small multiple of the last check alone.
+/// Batch means and the independent interval take linear time, and cost little either way.
+pub const PRECISION_CHECK_GROWTH:f64 = 1.25;
+
+/// Which speed is sampled.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Measure {
+    /// The games completed within each sample window, over the window's length.
+    Interval,
+    /// The games completed since the start of the run, priming included,
+    /// over the time since; a smoothed average that hides variability.
+    Cumulative,
+}
+
+/// Displays the measure as it is given on the command line.
+impl fmt::Display for Measure {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Measure::Interval => write!(f, "interval"),
+            Measure::Cumulative => write!(f, "cumulative"),
+        }
     }
+}
+
+/// When adaptive priming counts the speed as steady.
+///
+/// At each sample the rolling speed, the speed over the last `window`, is found;
+/// the speed is steady once the rolling speeds of the last `window` have
+/// a coefficient of variation below `cov`.
+#[derive(Clone, Copy)]
+pub struct SteadyState {
+    /// In nanoseconds.
+    pub window: u64,
+    pub cov: f64,
+}
+
+/// Settings for a benchmark run.
+///
+/// Every time here is in nanoseconds; the frequencies are the
+/// time between two samples, or two updates of the progress line.
+#[derive(Clone)]
+pub struct BenchmarkConfig {
+    pub threads: usize,
+    /// The factor `prime_time` and `sample_time` were scaled by.
+    pub multiplier: f64,
+    pub prime_time: u64,
+    pub sample_time: u64,
+    /// Ends priming as soon as the speed is steady; `prime_time` is then the longest it may last.
+    pub steady_state: Option<SteadyState>,
+    /// Ends sampling as soon as the confidence interval's half-width, relative to the mean,
+    /// is below this; `sample_time` is then the longest sampling may last.
+    pub precision: Option<f64>,
+    pub sample_frequency: u64,
+    pub display_frequency: u64,
+    /// Seeds the game generators, so a run can be replayed.
+    ///
+    /// Worker `i` uses `seed + i`; with no seed, each worker uses `rand::thread_rng`.
+    pub seed: Option<u64>,
+    /// Pins the workers to CPUs; without it, they go where the scheduler puts them.
+    pub affinity: Option<Affinity>,
+    /// The confidence level of the interval of the mean, such as 0.999.
+    pub confidence: f64,
+    /// How that interval is found.
+    pub interval: IntervalMethod,
+    /// The speed that is sampled, and that the statistics and rank are based on.
+    pub measure: Measure,
+    /// The rules the workers play by.
+    pub rules: Rules,
+}
+
+impl BenchmarkConfig {
+
+    /// The default settings, with the priming and sampling time scaled by `multiplier`.
+    ///
+    /// Any field may be changed afterwards; for example, setting `prime_time`
+    /// directly leaves `sample_time` as it was.
+    pub fn new(threads: usize, multiplier: f64) -> BenchmarkConfig {
+        let mut prime_time:u64 = PRIME_TIME;
+        let mut sample_time:u64 = SAMPLE_TIME;
+
+        if multiplier != 1.00 {
+            prime_time = (prime_time as f64 * multiplier) as u64;
+            sample_time = (sample_time as f64 * multiplier) as u64;
+        }
+
+        BenchmarkConfig {
+            threads,
+            multiplier,
+            prime_time,
+            sample_time,
+            steady_state: None,
+            precision: None,
+            sample_frequency: SAMPLE_FREQUENCY,
+            display_frequency: DISPLAY_FREQUENCY,
+            seed: None,
+            affinity: None,
+            confidence: interval::CONFIDENCE,
+            interval: IntervalMethod::BatchMeans,
+            measure: Measure::Interval,
+            rules: Rules::new(),
+        }
+    }
+
+}
 
-    let end_time:u64 = prime_time + sample_time;
+/// Runs the benchmark and returns its results.
+///
+/// The worker threads play the WarGame while this thread monitors
+/// their progress; nothing but the progress line is printed here,
+/// and it goes to stderr. The results are left to the caller to render.
+pub fn benchmark(config: &BenchmarkConfig) -> BenchmarkReport {
 
-    let sample_size:u64 = sample_time / SAMPLE_FREQUENCY;
+    let threads = config.threads;
+    let prime_time = config.prime_time;
+    let sample_time = config.sample_time;
+
+    let mut host = HostInfo::collect();
+
+    // the workers start playing right away
+    let cpus = config.affinity.as_ref().and_then(|a| affinity::plan(a, threads));
+    let workers = Workers::start(threads, config.seed, cpus, &config.rules);
+
+    // moved to follow priming when it ends early;
+    // saturating, since a caller may ask for more time than fits
+    let mut end_time:u64 = prime_time.saturating_add(sample_time);
+
+    // with adaptive priming, whether the speed was steady before the cap
+    let mut steady:Option<bool> = config.steady_state.map(|_| false);
+    let mut warmup_time:u64 = prime_time;
+
+    // with adaptive sampling, whether the precision was reached before the cap
+    let mut precise:Option<bool> = config.precision.map(|_| false);
+    // set when sampling starts
+    let mut last_precision_check:u64 = 0;
+    // the sample count the next check waits for
+    let mut next_precision_check:usize = 2;
+
+    let sample_size:u64 = (sample_time / config.sample_frequency).min(PREALLOCATED_SAMPLES);
 
     // samples used for statistics calculations
     let mut samples = Vec::with_capacity(sample_size as usize);
 
+    // every sample taken, priming included, for the time series
+    let mut series = Vec::with_capacity(((end_time / config.sample_frequency) + 1).min(PREALLOCATED_SAMPLES) as usize);
+
     let start_time:u64 = precise_time_ns();
     let mut current_time:u64;
     let mut elapsed_time:u64;
 
     let mut last_display_time:u64 = start_time;
     let mut last_sample_time:u64 = start_time;
+    let mut last_sample_games:u64 = 0;
 
     let mut phase:u64 = 1;
 
-    let mut total_games:u64 = 1;
+    let mut total_games:u64;
 
     let mut speed:f64;
 
@@ -69,9 +216,15 @@ pub fn benchmark(threads: usize, multiplier: f64) {
     let mut maximum_speed:f64 = 0.0;
     let mut minimum_speed:f64 = 0.0;
 
+    // games each worker completed during the sampling phase
+    let mut thread_start_games:Vec<u64> = Vec::new();
+    let mut thread_start_time:u64 = start_time;
+    let mut thread_games:Vec<u64> = Vec::new();
+    let mut thread_time:u64 = 0;
+
     'monitor: loop {
 
-        total_games = total_games + get_games(&completion_receivers);
+        total_games = workers.games();
 
         // time calculations
         current_time = precise_time_ns();
@@ -79,11 +232,17 @@ pub fn benchmark(threads: usize, multiplier: f64) {
 
         speed = total_games as f64 / elapsed_time as f64;
 
-        if phase == 1 && elapsed_time >= prime_time {
+        if phase == 1 && (elapsed_time >= prime_time || steady == Some(true)) {
             phase = 2;
+            warmup_time = elapsed_time;
+            end_time = elapsed_time.saturating_add(sample_time);
             // proper setting of min/max
             maximum_speed = speed;
             minimum_speed = speed;
+            // each worker's games are counted from here
+            thread_start_games = workers.games_per_thread();
+            thread_start_time = current_time;
+            last_precision_check = current_time;
         } else if phase == 2 {
 
             if maximum_speed < speed {
@@ -93,51 +252,113 @@ pub fn benchmark(threads: usize, multiplier: f64) {
                 minimum_speed = speed;
             }
 
-            if elapsed_time >= end_time {
+            if elapsed_time >= end_time || precise == Some(true) {
                 phase = 3;
+                // to here
+                thread_games = workers.games_per_thread().iter().zip(thread_start_games.iter()).map(|(e, s)| e - s).collect();
+                thread_time = current_time - thread_start_time;
             }
 
         } else if phase == 4 {
             break 'monitor;
         }
 
-        if phase == 2 && (current_time - last_sample_time) > SAMPLE_FREQUENCY {
+        if phase <= 2 && (current_time - last_sample_time) > config.sample_frequency {
+            // games completed within this sample window alone
+            let interval_speed = (total_games - last_sample_games) as f64
+                / (current_time - last_sample_time) as f64;
+
             last_sample_time = current_time;
-            samples.push(speed);
+            last_sample_games = total_games;
+
+            if phase == 2 {
+                samples.push(match config.measure {
+                    Measure::Interval => interval_speed,
+                    Measure::Cumulative => speed,
+                });
+            }
+
+            series.push(SeriesSample {
+                time: elapsed_time,
+                games: total_games,
+                interval_speed,
+                speed,
+                phase,
+            });
+
+            if phase == 2 && current_time - last_precision_check >= PRECISION_CHECK && samples.len() >= next_precision_check {
+                if let Some(target) = config.precision {
+                    last_precision_check = current_time;
+                    next_precision_check = (samples.len() as f64 * PRECISION_CHECK_GROWTH).ceil() as usize;
+                    let (lower, upper) = interval::confidence_interval(&samples, config.interval, config.confidence, config.seed);
+                    if relative_half_width(lower, upper) < target {
+                        precise = Some(true);
+                    }
+                }
+            }
+
+            if phase == 1 {
+                if let Some(ref s) = config.steady_state {
+                    if is_steady(&series, s) {
+                        steady = Some(true);
+                    }
+                }
+            }
         }
 
-        if (current_time - last_display_time) > DISPLAY_FREQUENCY {
+        if (current_time - last_display_time) > config.display_frequency {
             last_display_time = current_time;
 
             if phase == 1 {
-				print!("\r{}. priming | et = {}s; g = {}; s = {:.5} g/ms; \t",
+				eprint!("\r{}. priming | et = {}s; g = {}; s = {:.5} g/ms; \t",
                 phase, elapsed_time / NS, total_games, speed * MS as f64)
 			} else if phase == 2 {
-				print!("\r{}. sampling | et = {}s; g = {}; s = {:.5} g/ms; t = {}; \t",
+				eprint!("\r{}. sampling | et = {}s; g = {}; s = {:.5} g/ms; t = {}; \t",
 				phase, elapsed_time / NS, total_games, speed * MS as f64, samples.len())
-			} else if phase == 3 {
-				phase = 4;
-				// intentionally blank line
-				print!("\r{}. done                                                                 \t",
-				phase)
 			}
 
-            // rust is line buffered, so force output
-            io::stdout().flush().unwrap();
+            // stderr keeps stdout clean for the results; force output anyway
+            io::stderr().flush().unwrap();
 
         }
 
+        // not left to the next display, so the run ends when sampling does
+        if phase == 3 {
+            phase = 4;
+            // intentionally blank line
+            eprint!("\r{}. done                                                                 \t",
+            phase);
+            io::stderr().flush().unwrap();
+        }
+
     }
 
-    // Rust requires the threads be manually ended, and the channels specifically closed.
-    let _ = stop_threads(threads, &mut terminate_senders, &mut termination_receivers);
+    // the workers finish their current game, then end
+    let placement = workers.stop();
+    host.finish();
 
     // calculations
 
-    const T_SCORE:f64 = 3.291; // 99.9% t-score
     const ONE_PERCENT:f64 = 0.01; // 1%
     const TEN_PERCENT:f64 = 0.1; // 10%
 
+    let thread_speeds:Vec<f64> = thread_games.iter().map(|&g| g as f64 / thread_time as f64).collect();
+    let sample_speed:f64 = thread_games.iter().sum::<u64>() as f64 / thread_time as f64;
+
+    // the cumulative speed is tracked continuously above,
+    // but interval speeds exist only as samples
+    if config.measure == Measure::Interval && !samples.is_empty() {
+        minimum_speed = samples.iter().fold(f64::INFINITY, |a, &b| a.min(b));
+        maximum_speed = samples.iter().fold(0f64, |a, &b| a.max(b));
+    }
+
+    // the speed the samples are checked against, and the score is rounded from;
+    // the same as `BenchmarkReport::measured_speed`
+    let checked_speed:f64 = match config.measure {
+        Measure::Interval => sample_speed,
+        Measure::Cumulative => speed,
+    };
+
     let mean:f64 = get_mean(&samples);
     let median:f64 = get_median(&samples);
     let stdev:f64 = get_standard_deviation(&samples, mean);
@@ -154,148 +375,98 @@ pub fn benchmark(threads: usize, multiplier: f64) {
     let one_sigma_upper:f64 = mean + stdev;
     let one_sigma_delta:f64 = one_sigma_upper - one_sigma_lower;
 
-    let ci_lower:f64 = mean - (T_SCORE * (stdev / (samples.len() as f64).sqrt()));
-    let ci_upper:f64 = mean + (T_SCORE * (stdev / (samples.len() as f64).sqrt()));
+    let (ci_lower, ci_upper) = interval::confidence_interval(&samples, config.interval, config.confidence, config.seed);
     let ci_delta:f64 = ci_upper - ci_lower;
+    let effective_samples:f64 = interval::effective_sample_size(&samples);
 
     let mut criteria = HashMap::new();
 
     criteria.insert("1", mean_median_delta < stdev);
     criteria.insert("2", min_max_delta < max_ten_percent);
     criteria.insert("3", cov < ONE_PERCENT);
-    criteria.insert("4", one_sigma_lower < speed && speed < one_sigma_upper);
-    criteria.insert("5", ci_lower < speed && speed < ci_upper);
-
-
-    // show results
-    println!("\n---\n");
-
-    println!("Samples: {:9}", samples.len());
-    println!("Mean:\t {:9.5}", toms(mean));
-    println!("Median:\t {:9.5}", toms(median));
-    println!("S.D.:\t {:9.5}", toms(stdev));
-    println!("C.O.V.:\t {:9.5}", cov);
-
-    println!("---");
-
-    println!("Min-Max:\t < {:9.5} - {:9.5} > Δ {:9.5}",
-        toms(minimum_speed),
-        toms(maximum_speed),
-        toms(min_max_delta)
-    );
-
-    println!("1-σ:\t\t < {:9.5} - {:9.5} > Δ {:9.5}",
-        toms(one_sigma_lower),
-        toms(one_sigma_upper),
-        toms(one_sigma_delta)
-    );
-
-    println!("μ-Median:\t < {:9.5} - {:9.5} > Δ {:9.5}",
-		toms(mm_lower),
-		toms(mm_upper),
-		toms(mean_median_delta)
-    );
-
-	println!("99.9%% CI:\t < {:9.5} - {:9.5} > Δ {:9.5}",
-		toms(ci_lower),
-		toms(ci_upper),
-		toms(ci_delta)
-    );
-
-    println!("---");
-
-    println!("Threads: {}", threads);
-	println!("Multiplier: {:.2}", multiplier);
-	println!("Speed: {:.5} g/ms", toms(speed));
-	println!("Games: {}", total_games);
-	println!("Duration: {:.1}s", (elapsed_time as f64 / NS as f64));
-
-	println!("---");
-
-	println!("Rank: ({}/{}) {}", rank_passes(&mut criteria), criteria.len(), rank_letter(&mut criteria));
-	println!("Rank Criteria: {}", rank_reason(&mut criteria));
-
-	println!("---");
-
-	println!("Score: {}", toms(speed).round());
+    criteria.insert("4", one_sigma_lower < checked_speed && checked_speed < one_sigma_upper);
+    criteria.insert("5", ci_lower < checked_speed && checked_speed < ci_upper);
+
+    BenchmarkReport {
+        config: config.clone(),
+        mean,
+        median,
+        stdev,
+        cov,
+        minimum_speed,
+        maximum_speed,
+        min_max_delta,
+        one_sigma_lower,
+        one_sigma_upper,
+        one_sigma_delta,
+        mm_lower,
+        mm_upper,
+        mean_median_delta,
+        ci_lower,
+        ci_upper,
+        ci_delta,
+        effective_samples,
+        speed,
+        sample_speed,
+        warmup_time,
+        steady_state: steady,
+        precision: relative_half_width(ci_lower, ci_upper),
+        precision_reached: precise,
+        total_games,
+        elapsed_time,
+        rank_passes: rank_passes(&criteria),
+        rank_letter: rank_letter(&criteria),
+        rank_reason: rank_reason(&criteria),
+        score: toms(checked_speed).round(),
+        criteria,
+        samples,
+        series,
+        thread_min_max_ratio: get_min_max_ratio(&thread_speeds),
+        thread_jain_index: get_jain_index(&thread_speeds),
+        thread_games,
+        thread_speeds,
+        placement,
+        host,
+    }
 
 }
 
-fn toms(f: f64) -> f64 {
-    return f * MS as f64;
+/// Half the width of an interval, relative to its middle.
+fn relative_half_width(lower: f64, upper: f64) -> f64 {
+    (upper - lower) / (upper + lower)
 }
 
-fn create_threads(threads: usize, ts: &mut Vec<Sender<u32>>, tr: &mut Vec<Receiver<u32>>, c: &mut Vec<Receiver<u32>>) {
-
-    for i in 0..threads {
-
-        let (c_tx, c_rx): (Sender<u32>, Receiver<u32>) = channel();
-        let (ts_tx, ts_rx): (Sender<u32>, Receiver<u32>) = channel();
-        let (tr_tx, tr_rx): (Sender<u32>, Receiver<u32>) = channel();
-
-        ts.push(ts_tx);
-        tr.push(tr_rx);
-        c.push(c_rx);
-
-        thread::spawn(move || {
-            let thread_id = i;
-            // tight loop
-
-            // make a random generator for this
-            // thread only and supply that to each game
-            let mut rng = rand::thread_rng();
-
-            loop {
-                // the entire point of this: run the wargame
-                wg::game(&mut rng);
-                // completion gets incremented
-                let _ = c_tx.send(1);
-                // then the termination signal is checked, and if is available, loop is broken
-                let r = ts_rx.try_recv();
-                match r {
-                    Ok(r) => {if r == 1 {break;}}
-                    Err(_) => {}
-                }
-            }
-            // the termination success signal is sent
-            let _ = tr_tx.send(thread_id as u32);
-        });
+/// Whether the speed has settled, by the rules of `SteadyState`.
+fn is_steady(series: &[SeriesSample], steady: &SteadyState) -> bool {
+    let last = match series.last() {
+        Some(s) => s.time,
+        None => return false
+    };
+    // a window of rolling speeds needs two windows of samples
+    if series[0].time + 2 * steady.window > last {
+        return false;
     }
-}
 
-fn stop_threads(threads: usize, ts: &mut Vec<Sender<u32>>, tr: &mut Vec<Receiver<u32>>) -> usize {
-    for s in ts.iter() {
-        let _ = s.send(1);
-    }
-    let mut end_collection:usize = 0;
-    'end:loop {
-        for r in tr.iter() {
-            let rv: usize = match r.recv() {
-                Ok(_) => 1,
-                Err(_) => 0
-            };
-            end_collection = end_collection + rv;
-        }
-        if end_collection == threads {
-            break 'end;
-        }
+    let recent = series.partition_point(|s| s.time + steady.window < last);
+    let rolling: Vec<f64> = series[recent..].iter().map(|s| {
+        // the latest sample at least a window before this one
+        let before = &series[series.partition_point(|b| b.time + steady.window <= s.time) - 1];
+        (s.games - before.games) as f64 / (s.time - before.time) as f64
+    }).collect();
+    if rolling.len() < 2 {
+        return false;
     }
-    return end_collection;
+
+    let mean = get_mean(&rolling);
+    get_coefficient_of_variation(mean, get_standard_deviation(&rolling, mean)) < steady.cov
 }
 
-fn get_games(crx: &[Receiver<u32>]) -> u64 {
-    let mut total = 0;
-    for i in crx.iter() {
-        let r = match i.try_recv() {
-            Ok(x) => x,
-            Err(_) => 0
-        };
-        total = total + r as u64;
-    }
-    return total;
+/// Converts a speed in games per nanosecond to games per millisecond.
+pub fn toms(f: f64) -> f64 {
+    return f * MS as f64;
 }
 
-fn rank_passes(criteria: &mut HashMap<&str, bool>) -> usize {
+fn rank_passes(criteria: &HashMap<&str, bool>) -> usize {
     let mut n:usize = 0;
     for (_, &b) in criteria.iter() {
         if b {
@@ -305,7 +476,7 @@ fn rank_passes(criteria: &mut HashMap<&str, bool>) -> usize {
     return n;
 }
 
-fn rank_letter(criteria: &mut HashMap<&str, bool>) -> String {
+fn rank_letter(criteria: &HashMap<&str, bool>) -> String {
     let n:usize = rank_passes(criteria);
     let str = match n {
         5 => "A+",
@@ -318,7 +489,7 @@ fn rank_letter(criteria: &mut HashMap<&str, bool>) -> String {
     return str.to_string();
 }
 
-fn rank_reason(criteria: &mut HashMap<&str, bool>) -> String {
+fn rank_reason(criteria: &HashMap<&str, bool>) -> String {
     let reason;
     let passes = rank_passes(criteria);
     if passes == 0 {
@@ -336,11 +507,34 @@ fn rank_reason(criteria: &mut HashMap<&str, bool>) -> String {
     return reason;
 }
 
+/// The slowest over the fastest; 1 when every value is the same.
+fn get_min_max_ratio(values: &[f64]) -> f64 {
+    let min = values.iter().fold(f64::INFINITY, |a, &b| a.min(b));
+    let max = values.iter().fold(0f64, |a, &b| a.max(b));
+    min / max
+}
+
+/// Jain's fairness index, `(Σx)² / (n Σx²)`;
+/// 1 when every value is the same, down to `1/n` when one value holds everything.
+fn get_jain_index(values: &[f64]) -> f64 {
+    let mut total = 0f64;
+    let mut total_squares = 0f64;
+    for v in values.iter() {
+        total += v;
+        total_squares += v * v;
+    }
+    (total * total) / (values.len() as f64 * total_squares)
+}
+
 fn get_coefficient_of_variation(mean: f64, stdev: f64) -> f64 {
     return stdev / mean;
 }
 
-fn get_standard_deviation(samples: &[f64], mean: f64) -> f64 {
+/// The sample standard deviation; there is none, only NaN, with fewer than two samples.
+pub(crate) fn get_standard_deviation(samples: &[f64], mean: f64) -> f64 {
+    if samples.len() < 2 {
+        return f64::NAN;
+    }
     let mut total_stdev = 0f64;
     for s in samples.iter() {
         total_stdev = total_stdev + (s - mean).powi(2);
@@ -349,7 +543,11 @@ fn get_standard_deviation(samples: &[f64], mean: f64) -> f64 {
     return stdev;
 }
 
+/// The median; there is none, only NaN, without samples.
 fn get_median(samples: &[f64]) -> f64 {
+    if samples.is_empty() {
+        return f64::NAN;
+    }
     let mut s = samples.to_vec();
 
     // this is required because core::cmp::Ord is not
@@ -361,7 +559,7 @@ fn get_median(samples: &[f64]) -> f64 {
     let median:f64;
     if length % 2 == 0 {
         let a = s[length / 2 - 1];
-        let b = s[length / 2 + 2];
+        let b = s[length / 2];
         median = (a+b)/2 as f64;
     } else {
         median = s[length / 2];
@@ -369,7 +567,7 @@ fn get_median(samples: &[f64]) -> f64 {
     return median;
 }
 
-fn get_mean(samples: &[f64]) -> f64 {
+pub(crate) fn get_mean(samples: &[f64]) -> f64 {
     let mut total_mean:f64 = 0f64;
     for s in samples.iter() {
         total_mean = total_mean + s;
@@ -377,3 +575,79 @@ fn get_mean(samples: &[f64]) -> f64 {
     let mean = total_mean / samples.len() as f64;
     return mean;
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn median_takes_the_middle_samples() {
+        assert_eq!(get_median(&[3.0, 1.0, 2.0]), 2.0);
+        assert_eq!(get_median(&[4.0, 1.0, 3.0, 2.0]), 2.5);
+        assert_eq!(get_median(&[1.0, 2.0]), 1.5);
+        assert_eq!(get_median(&[7.0]), 7.0);
+        assert!(get_median(&[]).is_nan());
+    }
+
+    #[test]
+    fn standard_deviation_needs_two_samples() {
+        let samples = [2.0, 4.0, 4.0, 4.0, 5.0, 5.0, 7.0, 9.0];
+        let mean = get_mean(&samples);
+        assert_eq!(mean, 5.0);
+        assert!((get_standard_deviation(&samples, mean) - (32.0f64 / 7.0).sqrt()).abs() < 1e-12);
+        assert!(get_standard_deviation(&[1.0], 1.0).is_nan());
+        assert!(get_standard_deviation(&[], 0.0).is_nan());
+    }
+
+    /// A sample every 10ms, with the games of each window played at `rates`, in games per millisecond.
+    fn series(rates: &[u64]) -> Vec<SeriesSample> {
+        let mut games = 0;
+        rates.iter().enumerate().map(|(i, &rate)| {
+            games += rate * 10;
+            SeriesSample {time: (i as u64 + 1) * 10 * MS, games, interval_speed: 0.0, speed: 0.0, phase: 1}
+        }).collect()
+    }
+
+    #[test]
+    fn steady_speeds_end_priming() {
+        let steady = SteadyState {window: 100 * MS, cov: 0.01};
+        assert!(is_steady(&series(&[100; 30]), &steady));
+        // the rolling speeds need two windows of samples
+        assert!(!is_steady(&series(&[100; 15]), &steady));
+        assert!(!is_steady(&[], &steady));
+        // a speed still climbing is not steady
+        let mut climbing = vec![100; 20];
+        climbing.extend_from_slice(&[200; 10]);
+        assert!(!is_steady(&series(&climbing), &steady));
+    }
+
+    #[test]
+    fn precision_is_relative_to_the_middle_of_the_interval() {
+        assert!((relative_half_width(9.0, 11.0) - 0.1).abs() < 1e-12);
+        assert!((relative_half_width(2.0, 2.0)).abs() < 1e-12);
+    }
+
+    #[test]
+    fn adaptive_sampling_ends_once_precise() {
+        let mut config = BenchmarkConfig::new(1, 1.0);
+        config.prime_time = 100 * MS;
+        config.sample_time = 20 * NS;
+        // any interval of positive speeds is within 100%
+        config.precision = Some(1.0);
+        let report = benchmark(&config);
+        assert_eq!(report.precision_reached, Some(true));
+        assert!(report.precision < 1.0);
+        // sampling ended long before the cap
+        assert!(report.samples.len() < (5 * NS / config.sample_frequency) as usize);
+    }
+
+    #[test]
+    fn score_rounds_the_measured_speed() {
+        for &measure in [Measure::Interval, Measure::Cumulative].iter() {
+            let mut config = BenchmarkConfig::new(1, 0.01);
+            config.measure = measure;
+            let report = benchmark(&config);
+            assert_eq!(report.score, toms(report.measured_speed()).round());
+        }
+    }
+}
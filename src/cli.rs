@@ -0,0 +1,877 @@
+//! Command line parsing for the `wargame-rust` binary.
+
+use std::str::FromStr;
+
+use wargame_rust::affinity;
+use wargame_rust::affinity::Affinity;
+use wargame_rust::interval::IntervalMethod;
+use wargame_rust::wg::{DeckSpec, Rules, ShortWar, Value, Winnings};
+use wargame_rust::benchmark;
+use wargame_rust::benchmark::{BenchmarkConfig, Measure, SteadyState};
+
+const NS:f64 = 1000000000.0;
+
+/// The longest a run may prime and sample for, in seconds: about 11 days.
+const MAX_SECONDS:f64 = 1000000.0;
+
+/// The most samples a run may take, priming included; each is kept until the run ends.
+const MAX_SAMPLES:f64 = 10000000.0;
+
+/// How the results are written to stdout.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Format {
+	Text,
+	Json
+}
+
+/// Settings for a benchmark run.
+pub struct Options {
+	pub config: BenchmarkConfig,
+	pub format: Format,
+	pub csv: Option<String>,
+	/// Append the results to this history file.
+	pub history: Option<String>,
+	/// Include the speed samples in the JSON results and history.
+	pub save_samples: bool,
+	/// Play exactly this many games instead of running for a set time.
+	pub games: Option<u64>
+}
+
+/// Settings for the `stats` command.
+pub struct StatsOptions {
+	pub games: u64,
+	pub threads: usize,
+	pub seed: Option<u64>,
+	pub bins: usize,
+	pub rules: Rules,
+	pub format: Format
+}
+
+/// Settings for the `compare` command.
+pub struct CompareOptions {
+	pub baseline: String,
+	pub current: String,
+	/// The largest drop in mean speed that still passes, as a fraction.
+	pub threshold: f64,
+	/// The p-value below which a difference is taken as real.
+	pub alpha: f64,
+	pub format: Format
+}
+
+/// What the binary was asked to do.
+pub enum Command {
+	Benchmark(Options),
+	Stats(StatsOptions),
+	/// A benchmark run for each of the thread counts.
+	Sweep(Options, Vec<usize>),
+	Compare(CompareOptions),
+	Help,
+	Version
+}
+
+/// The text shown for `--help`.
+pub fn usage() -> String {
+	format!("WarGame Rust {}
+
+Usage:
+    wargame-rust [options] [threads] [multiplier]
+    wargame-rust stats [stats options]
+    wargame-rust sweep [options]
+    wargame-rust compare <baseline> <current> [compare options]
+
+Options:
+    -t, --threads <n>        worker threads (default: 1)
+    -m, --multiplier <x>     scales the priming and sampling time (default: 1.00)
+        --prime-secs <s>     priming time in seconds (default: 10, scaled)
+        --sample-secs <s>    sampling time in seconds (default: 50, scaled)
+        --precision <pct>    end sampling early once the confidence interval is
+                             within this of the mean; the sampling time
+                             becomes the longest sampling may last
+        --steady-cov <pct>   end priming early once the rolling speed varies by
+                             less than this (default: 1); the priming time
+                             becomes the longest priming may last
+        --steady-window <s>  the rolling window for --steady-cov, in seconds
+                             (default: 1)
+        --sample-frequency <hz>
+                             speed samples per second (default: 200)
+        --display-frequency <hz>
+                             progress updates per second (default: 10)
+        --measure <m>        the speed to sample: `interval`, each sample window
+                             alone, or `cumulative`, since the start of the run
+                             (default: interval)
+        --confidence <pct>   confidence level of the interval of the mean
+                             (default: 99.9)
+        --interval <method>  how that interval is found: `batch-means`,
+                             `bootstrap` or `independent` (default: batch-means)
+        --seed <n>           seed the games; worker i uses seed + i
+        --affinity <a>       pin workers to CPUs (Linux only): `round-robin`,
+                             `compact`, `scatter`, or a list such as `0,2,4-7`
+        --format <format>    results format, `text` or `json` (default: text)
+        --csv <path>         write every speed sample to a CSV file
+        --history <path>     append the results to a JSON lines history file
+        --save-samples       include the speed samples in the JSON results and
+                             history, so `compare` can test them
+    -n, --games <n>          play exactly n games and time them, instead
+                             of priming and sampling
+    -h, --help               show this help
+    -V, --version            show the version
+
+The positional `threads` and `multiplier` are kept for compatibility;
+they may not be combined with their named forms.
+An explicit --prime-secs or --sample-secs is not scaled by the multiplier.
+Priming and sampling may last at most 1000000 seconds in all,
+and take at most 10000000 samples.
+
+A sweep takes the options above, but --threads is a list of thread counts,
+such as `1,2,4,8` or `1-8` (default: powers of two up to the number of CPUs).
+A single thread run is always included.
+
+Stats options, for studying the game rather than its speed:
+    -n, --games <n>          games to play (default: 10000)
+    -t, --threads <n>        threads to play them on (default: 1)
+        --seed <n>           seed the games; thread i uses seed + i
+        --bins <n>           histogram bins (default: 10)
+        --format <format>    results format, `text` or `json` (default: text)
+
+Rules options, for both runs and stats (default: the classic rules):
+        --players <n>        players the deck is dealt between, at least 2;
+                             a tie for the highest card is a war among the
+                             tied players (default: 2)
+        --deck <deck>        the cards of one deck: `standard`, 52 cards;
+                             `piquet`, the 32 from Seven to Ace; or counts of
+                             each value, such as `7:4,8:4,9:4,10:4,J:4,Q:4,K:4,A:8`
+                             (default: standard)
+        --decks <n>          decks shuffled together (default: 1)
+        --jokers <n>         Jokers added, which beat every other card
+                             (default: 0)
+        --face-down <n>      cards laid face down in a war (default: 3)
+        --short-war <rule>   when a player has too few cards for a war: `lose`,
+                             `use-last` to turn up their last card, or `draw`
+                             (default: lose)
+        --winnings <order>   the order won cards are taken in: `shuffled` or
+                             `in-order` (default: shuffled)
+        --ace <high|low>     whether an Ace beats a King or loses to a Two
+                             (default: high)
+        --max-turns <n>      stop a game after n turns, as a draw
+        --detect-cycles      stop a game, as a draw, when the hands repeat;
+                             only with `--winnings in-order`, and always on
+                             with it unless `--max-turns` is given
+
+Compare options, for measuring a run against a baseline run:
+    <baseline>, <current>    a results file, or a history file; `path:n` picks
+                             the nth run, and negative n counts from the last
+                             (default: the last run)
+        --threshold <pct>    largest drop in mean speed that passes (default: 5)
+        --alpha <p>          p-value below which a difference is real
+                             (default: 0.001)
+        --format <format>    results format, `text` or `json` (default: text)
+The exit status is 1 when the current run fails.", env!("CARGO_PKG_VERSION"))
+}
+
+/// A single argument, as seen by `Arguments`.
+enum Argument {
+	Positional(String),
+	/// An option by name, with its value when given as `--name=value`.
+	Option(String, Option<String>)
+}
+
+/// Walks the arguments, telling options from positional arguments.
+struct Arguments<'a> {
+	args: &'a [String],
+	i: usize
+}
+
+impl<'a> Arguments<'a> {
+
+	fn new(args: &'a [String]) -> Arguments<'a> {
+		Arguments {args, i: 0}
+	}
+
+	fn next(&mut self) -> Option<Argument> {
+		if self.i >= self.args.len() {
+			return None;
+		}
+		let arg = &self.args[self.i];
+		self.i += 1;
+
+		// negative numbers are values, not options
+		if !arg.starts_with('-') || arg == "-" || arg.parse::<f64>().is_ok() {
+			return Some(Argument::Positional(arg.clone()));
+		}
+
+		// both `--name value` and `--name=value` are accepted
+		match arg.find('=') {
+			Some(p) if arg.starts_with("--") => Some(Argument::Option(arg[..p].to_string(), Some(arg[p + 1..].to_string()))),
+			_ => Some(Argument::Option(arg.clone(), None))
+		}
+	}
+
+	/// Checks that a flag, which takes no value, was not given one as `--name=value`.
+	fn flag(&self, name: &str, inline: Option<String>) -> Result<(), String> {
+		match inline {
+			Some(v) => Err(format!("{} does not take a value, not `{}`", name, v)),
+			None => Ok(())
+		}
+	}
+
+	/// The value of an option; either given inline, or the next argument.
+	fn value(&mut self, name: &str, inline: Option<String>) -> Result<String, String> {
+		if let Some(v) = inline {
+			return Ok(v);
+		}
+		if self.i >= self.args.len() {
+			return Err(format!("{} requires a value", name));
+		}
+		self.i += 1;
+		Ok(self.args[self.i - 1].clone())
+	}
+
+}
+
+/// The options of a benchmark run or sweep that take a value.
+const RUN_OPTIONS: &[&str] = &[
+	"-t", "--threads", "-m", "--multiplier", "--format", "--csv", "--history",
+	"--prime-secs", "--sample-secs", "--precision", "--steady-cov", "--steady-window",
+	"--sample-frequency", "--display-frequency", "--seed", "--affinity",
+	"--measure", "--confidence", "--interval", "-n", "--games"
+];
+
+/// The options of `stats` that take a value.
+const STATS_OPTIONS: &[&str] = &["-n", "--games", "-t", "--threads", "--seed", "--bins", "--format"];
+
+/// The rules options, for both runs and stats, that take a value.
+const RULES_OPTIONS: &[&str] = &[
+	"--players", "--deck", "--decks", "--jokers", "--face-down",
+	"--short-war", "--winnings", "--ace", "--max-turns"
+];
+
+/// The options of `compare` that take a value.
+const COMPARE_OPTIONS: &[&str] = &["--threshold", "--alpha", "--format"];
+
+/// Parses the arguments, without the program name.
+///
+/// Every problem is reported as an error rather than
+/// falling back to a default, so a typo never runs the wrong benchmark.
+pub fn parse(args: &[String]) -> Result<Command, String> {
+	if args.first().map(|a| a.as_str()) == Some("stats") {
+		return parse_stats(&args[1..]);
+	}
+	if args.first().map(|a| a.as_str()) == Some("compare") {
+		return parse_compare(&args[1..]);
+	}
+
+	// a sweep takes the benchmark options, but a list of thread counts
+	let sweep = args.first().map(|a| a.as_str()) == Some("sweep");
+	let args = if sweep {&args[1..]} else {args};
+	let mut thread_list: Option<Vec<usize>> = None;
+
+	let mut threads: Option<usize> = None;
+	let mut multiplier: Option<f64> = None;
+	let mut format = Format::Text;
+	let mut csv: Option<String> = None;
+	let mut history: Option<String> = None;
+	let mut save_samples = false;
+	let mut prime_secs: Option<f64> = None;
+	let mut sample_secs: Option<f64> = None;
+	let mut precision: Option<f64> = None;
+	let mut steady_cov: Option<f64> = None;
+	let mut steady_window: Option<f64> = None;
+	let mut sample_frequency: Option<f64> = None;
+	let mut display_frequency: Option<f64> = None;
+	let mut seed: Option<u64> = None;
+	let mut games: Option<u64> = None;
+	let mut affinity: Option<Affinity> = None;
+	let mut measure: Option<Measure> = None;
+	let mut confidence: Option<f64> = None;
+	let mut interval: Option<IntervalMethod> = None;
+	let mut rules = Rules::new();
+
+	let mut positional: Vec<String> = Vec::new();
+
+	let mut arguments = Arguments::new(args);
+	while let Some(arg) = arguments.next() {
+		let (name, inline) = match arg {
+			Argument::Positional(p) => {
+				positional.push(p);
+				continue;
+			},
+			Argument::Option(name, inline) => (name, inline)
+		};
+		let name = name.as_str();
+
+		// flags first, and nothing unknown is taken for an option wanting a value
+		match name {
+			"-h" | "--help" => return arguments.flag(name, inline).map(|_| Command::Help),
+			"-V" | "--version" => return arguments.flag(name, inline).map(|_| Command::Version),
+			"--save-samples" => {
+				arguments.flag(name, inline)?;
+				save_samples = true;
+				continue;
+			},
+			"--detect-cycles" => {
+				arguments.flag(name, inline)?;
+				rules.detect_cycles = true;
+				continue;
+			},
+			_ if !RUN_OPTIONS.contains(&name) && !RULES_OPTIONS.contains(&name) => {
+				return Err(format!("unknown option `{}`", name));
+			},
+			_ => {}
+		}
+
+		let value = arguments.value(name, inline)?;
+
+		match name {
+			"-t" | "--threads" if sweep => thread_list = Some(parse_thread_list(&value)?),
+			"-t" | "--threads" => threads = Some(parse_threads(&value)?),
+			"-m" | "--multiplier" => multiplier = Some(parse_multiplier(&value)?),
+			"--format" => format = parse_format(&value)?,
+			"--csv" => csv = Some(value),
+			"--history" => history = Some(value),
+			"--prime-secs" => prime_secs = Some(parse_seconds(name, &value, true)?),
+			"--sample-secs" => sample_secs = Some(parse_seconds(name, &value, false)?),
+			"--precision" => precision = Some(parse_precision(&value)?),
+			"--steady-cov" => steady_cov = Some(parse_percent("steady-cov", &value)?),
+			"--steady-window" => steady_window = Some(parse_seconds(name, &value, false)?),
+			"--sample-frequency" => sample_frequency = Some(parse_frequency(name, &value)?),
+			"--display-frequency" => display_frequency = Some(parse_frequency(name, &value)?),
+			"--seed" => seed = Some(parse_number("seed", &value)?),
+			"--affinity" => affinity = Some(parse_affinity(&value)?),
+			"--measure" => measure = Some(parse_measure(&value)?),
+			"--confidence" => confidence = Some(parse_confidence(&value)?),
+			"--interval" => interval = Some(parse_interval(&value)?),
+			"-n" | "--games" => games = Some(parse_count("games", &value)? as u64),
+			"--players" => rules.players = parse_players(&value)?,
+			"--deck" => rules.deck.counts = parse_deck(&value)?,
+			"--decks" => rules.deck.decks = parse_count("decks", &value)?,
+			"--jokers" => rules.deck.jokers = parse_number("jokers", &value)?,
+			"--face-down" => rules.face_down = parse_number("face-down", &value)?,
+			"--short-war" => rules.short_war = parse_short_war(&value)?,
+			"--winnings" => rules.winnings = parse_winnings(&value)?,
+			"--ace" => rules.ace_high = parse_ace(&value)?,
+			"--max-turns" => rules.max_turns = Some(parse_max_turns(&value)?),
+			_ => return Err(format!("unknown option `{}`", name))
+		}
+	}
+
+	if games.is_some() {
+		let timed = affinity.is_some() || multiplier.is_some() || prime_secs.is_some() || sample_secs.is_some()
+			|| steady_cov.is_some() || steady_window.is_some() || precision.is_some()
+			|| sample_frequency.is_some() || display_frequency.is_some() || positional.len() > 1 || csv.is_some()
+			|| history.is_some() || save_samples || measure.is_some() || confidence.is_some() || interval.is_some();
+		if timed {
+			return Err("--games cannot be combined with --affinity, --csv, --history, --save-samples, or the priming and sampling options".to_string());
+		}
+	}
+
+	if sweep {
+		if let Some(p) = positional.first() {
+			return Err(format!("unexpected argument `{}`", p));
+		}
+		if csv.is_some() || history.is_some() || save_samples || games.is_some() {
+			return Err("--csv, --history, --save-samples and --games cannot be used with a sweep".to_string());
+		}
+	}
+
+	if positional.len() > 2 {
+		return Err(format!("unexpected argument `{}`", positional[2]));
+	}
+	if let Some(t) = positional.first() {
+		if threads.is_some() {
+			return Err("threads given both by position and by --threads".to_string());
+		}
+		threads = Some(parse_threads(t)?);
+	}
+	if let Some(m) = positional.get(1) {
+		if multiplier.is_some() {
+			return Err("multiplier given both by position and by --multiplier".to_string());
+		}
+		multiplier = Some(parse_multiplier(m)?);
+	}
+
+	let multiplier = multiplier.unwrap_or(1.00);
+	check_run_time(
+		prime_secs.unwrap_or(benchmark::PRIME_TIME as f64 / NS * multiplier),
+		sample_secs.unwrap_or(benchmark::SAMPLE_TIME as f64 / NS * multiplier),
+		sample_frequency.unwrap_or(NS / benchmark::SAMPLE_FREQUENCY as f64)
+	)?;
+
+	let mut config = BenchmarkConfig::new(threads.unwrap_or(1), multiplier);
+	if let Some(s) = prime_secs {
+		config.prime_time = (s * NS) as u64;
+	}
+	if let Some(s) = sample_secs {
+		config.sample_time = (s * NS) as u64;
+	}
+	config.precision = precision;
+	check_rules(&rules)?;
+	config.rules = rules;
+	// either option turns on adaptive priming
+	if steady_cov.is_some() || steady_window.is_some() {
+		config.steady_state = Some(SteadyState {
+			window: steady_window.map_or(benchmark::STEADY_WINDOW, |s| (s * NS) as u64),
+			cov: steady_cov.unwrap_or(benchmark::STEADY_COV)
+		});
+	}
+	if let Some(hz) = sample_frequency {
+		config.sample_frequency = (NS / hz) as u64;
+	}
+	if let Some(hz) = display_frequency {
+		config.display_frequency = (NS / hz) as u64;
+	}
+	config.seed = seed;
+	config.affinity = affinity;
+	if let Some(m) = measure {
+		config.measure = m;
+	}
+	if let Some(c) = confidence {
+		config.confidence = c;
+	}
+	if let Some(i) = interval {
+		config.interval = i;
+	}
+
+	let options = Options {
+		config,
+		format,
+		csv,
+		history,
+		save_samples,
+		games
+	};
+
+	if sweep {
+		return Ok(Command::Sweep(options, thread_list.unwrap_or_else(default_thread_list)));
+	}
+	Ok(Command::Benchmark(options))
+}
+
+fn parse_stats(args: &[String]) -> Result<Command, String> {
+	let mut options = StatsOptions {
+		games: 10000,
+		threads: 1,
+		seed: None,
+		bins: 10,
+		rules: Rules::new(),
+		format: Format::Text
+	};
+
+	let mut arguments = Arguments::new(args);
+	while let Some(arg) = arguments.next() {
+		let (name, inline) = match arg {
+			Argument::Positional(p) => return Err(format!("unexpected argument `{}`", p)),
+			Argument::Option(name, inline) => (name, inline)
+		};
+		let name = name.as_str();
+
+		match name {
+			"-h" | "--help" => return arguments.flag(name, inline).map(|_| Command::Help),
+			"-V" | "--version" => return arguments.flag(name, inline).map(|_| Command::Version),
+			"--detect-cycles" => {
+				arguments.flag(name, inline)?;
+				options.rules.detect_cycles = true;
+				continue;
+			},
+			_ if !STATS_OPTIONS.contains(&name) && !RULES_OPTIONS.contains(&name) => {
+				return Err(format!("unknown option `{}`", name));
+			},
+			_ => {}
+		}
+
+		let value = arguments.value(name, inline)?;
+
+		match name {
+			"-n" | "--games" => options.games = parse_count("games", &value)? as u64,
+			"-t" | "--threads" => options.threads = parse_threads(&value)?,
+			"--seed" => options.seed = Some(parse_number("seed", &value)?),
+			"--bins" => options.bins = parse_count("bins", &value)?,
+			"--players" => options.rules.players = parse_players(&value)?,
+			"--deck" => options.rules.deck.counts = parse_deck(&value)?,
+			"--decks" => options.rules.deck.decks = parse_count("decks", &value)?,
+			"--jokers" => options.rules.deck.jokers = parse_number("jokers", &value)?,
+			"--face-down" => options.rules.face_down = parse_number("face-down", &value)?,
+			"--short-war" => options.rules.short_war = parse_short_war(&value)?,
+			"--winnings" => options.rules.winnings = parse_winnings(&value)?,
+			"--ace" => options.rules.ace_high = parse_ace(&value)?,
+			"--max-turns" => options.rules.max_turns = Some(parse_max_turns(&value)?),
+			"--format" => options.format = parse_format(&value)?,
+			_ => return Err(format!("unknown option `{}`", name))
+		}
+	}
+
+	check_rules(&options.rules)?;
+	Ok(Command::Stats(options))
+}
+
+fn parse_compare(args: &[String]) -> Result<Command, String> {
+	let mut threshold = 0.05;
+	let mut alpha = 0.001;
+	let mut format = Format::Text;
+	let mut runs: Vec<String> = Vec::new();
+
+	let mut arguments = Arguments::new(args);
+	while let Some(arg) = arguments.next() {
+		let (name, inline) = match arg {
+			Argument::Positional(p) => {
+				runs.push(p);
+				continue;
+			},
+			Argument::Option(name, inline) => (name, inline)
+		};
+		let name = name.as_str();
+
+		match name {
+			"-h" | "--help" => return arguments.flag(name, inline).map(|_| Command::Help),
+			"-V" | "--version" => return arguments.flag(name, inline).map(|_| Command::Version),
+			_ if !COMPARE_OPTIONS.contains(&name) => return Err(format!("unknown option `{}`", name)),
+			_ => {}
+		}
+
+		let value = arguments.value(name, inline)?;
+
+		match name {
+			"--threshold" => threshold = parse_percent("threshold", &value)?,
+			"--alpha" => alpha = parse_probability("alpha", &value)?,
+			"--format" => format = parse_format(&value)?,
+			_ => return Err(format!("unknown option `{}`", name))
+		}
+	}
+
+	if runs.len() != 2 {
+		return Err("compare needs a baseline and a current run".to_string());
+	}
+	let current = runs.pop().unwrap();
+	let baseline = runs.pop().unwrap();
+
+	Ok(Command::Compare(CompareOptions {
+		baseline,
+		current,
+		threshold,
+		alpha,
+		format
+	}))
+}
+
+fn parse_number<T: FromStr>(name: &str, value: &str) -> Result<T, String> {
+	value.trim().parse().map_err(|_| format!("{} must be a number, not `{}`", name, value))
+}
+
+/// A count that must be at least 1.
+fn parse_count(name: &str, value: &str) -> Result<usize, String> {
+	let n: usize = parse_number(name, value)?;
+	if n == 0 {
+		return Err(format!("{} must be at least 1", name));
+	}
+	Ok(n)
+}
+
+/// At least two players; whether there are cards enough for them is left to `check_rules`.
+fn parse_players(value: &str) -> Result<usize, String> {
+	let n: usize = parse_number("players", value)?;
+	if n < 2 {
+		return Err(format!("players must be at least 2, not {}", n));
+	}
+	Ok(n)
+}
+
+fn parse_max_turns(value: &str) -> Result<u32, String> {
+	let n: u32 = parse_number("max-turns", value)?;
+	if n == 0 {
+		return Err("max-turns must be at least 1".to_string());
+	}
+	Ok(n)
+}
+
+/// Every player must be dealt a card, and only in-order winnings can cycle.
+fn check_rules(rules: &Rules) -> Result<(), String> {
+	let cards = rules.deck.cards();
+	if cards < rules.players {
+		return Err(format!("a deck of {} cards is too few for {} players", cards, rules.players));
+	}
+	if rules.detect_cycles && rules.winnings != Winnings::InOrder {
+		return Err("--detect-cycles needs --winnings in-order".to_string());
+	}
+	Ok(())
+}
+
+/// `standard`, `piquet`, or a comma separated list of `value:count`, such as `K:4,A:8`.
+fn parse_deck(value: &str) -> Result<Vec<(Value, usize)>, String> {
+	match value {
+		"standard" => return Ok(DeckSpec::standard().counts),
+		"piquet" => return Ok(DeckSpec::piquet().counts),
+		_ => {}
+	}
+	let mut counts: Vec<(Value, usize)> = Vec::new();
+	for part in value.split(',') {
+		let (name, count) = part.split_once(':')
+			.ok_or_else(|| format!("deck must be `standard`, `piquet`, or counts such as `K:4,A:8`, not `{}`", value))?;
+		let v = parse_value(name.trim())?;
+		if counts.iter().any(|&(c, _)| c == v) {
+			return Err(format!("deck gives `{}` more than once", name.trim()));
+		}
+		counts.push((v, parse_number("deck count", count)?));
+	}
+	Ok(counts)
+}
+
+fn parse_value(name: &str) -> Result<Value, String> {
+	match name.to_lowercase().as_str() {
+		"2" => Ok(Value::Two),
+		"3" => Ok(Value::Three),
+		"4" => Ok(Value::Four),
+		"5" => Ok(Value::Five),
+		"6" => Ok(Value::Six),
+		"7" => Ok(Value::Seven),
+		"8" => Ok(Value::Eight),
+		"9" => Ok(Value::Nine),
+		"10" => Ok(Value::Ten),
+		"j" => Ok(Value::Jack),
+		"q" => Ok(Value::Queen),
+		"k" => Ok(Value::King),
+		"a" => Ok(Value::Ace),
+		"joker" => Ok(Value::Joker),
+		_ => Err(format!("a deck value must be 2 to 10, J, Q, K, A or Joker, not `{}`", name))
+	}
+}
+
+fn parse_threads(value: &str) -> Result<usize, String> {
+	parse_count("threads", value)
+}
+
+/// A comma separated list of thread counts and inclusive ranges, such as `1,2,4-8`.
+fn parse_thread_list(value: &str) -> Result<Vec<usize>, String> {
+	let mut list = Vec::new();
+	for item in value.split(',') {
+		match item.find('-') {
+			Some(p) => {
+				let first = parse_threads(&item[..p])?;
+				let last = parse_threads(&item[p + 1..])?;
+				if first > last {
+					return Err(format!("thread range `{}` is backwards", item));
+				}
+				list.extend(first..last + 1);
+			},
+			None => list.push(parse_threads(item)?)
+		}
+	}
+	Ok(list)
+}
+
+/// Powers of two up to the number of CPUs, and the number of CPUs itself.
+fn default_thread_list() -> Vec<usize> {
+	let cpus = affinity::cpu_count();
+	let mut list = Vec::new();
+	let mut n = 1;
+	while n < cpus {
+		list.push(n);
+		n *= 2;
+	}
+	list.push(cpus);
+	list
+}
+
+fn parse_affinity(value: &str) -> Result<Affinity, String> {
+	match value {
+		"round-robin" => Ok(Affinity::RoundRobin),
+		"compact" => Ok(Affinity::Compact),
+		"scatter" => Ok(Affinity::Scatter),
+		_ => affinity::parse_cpu_list(value).map(Affinity::Cores)
+			.map_err(|e| format!("affinity must be `round-robin`, `compact`, `scatter` or a list of CPUs; {}", e))
+	}
+}
+
+/// A percentage, given as `5` or `5%`, returned as a fraction.
+fn parse_percent(name: &str, value: &str) -> Result<f64, String> {
+	let percent: f64 = parse_number(name, value.trim_end_matches('%'))?;
+	if !(percent.is_finite() && percent >= 0.0) {
+		return Err(format!("{} must be a percentage of at least 0, not `{}`", name, value));
+	}
+	Ok(percent / 100.0)
+}
+
+/// A confidence level, given as a percentage such as `99.9`, returned as a fraction.
+fn parse_confidence(value: &str) -> Result<f64, String> {
+	let confidence = parse_percent("confidence", value)?;
+	if !(confidence > 0.0 && confidence < 1.0) {
+		return Err(format!("confidence must be a percentage between 0 and 100, not `{}`", value));
+	}
+	Ok(confidence)
+}
+
+fn parse_precision(value: &str) -> Result<f64, String> {
+	let precision = parse_percent("precision", value)?;
+	if precision.is_nan() || precision <= 0.0 {
+		return Err(format!("precision must be a percentage greater than 0, not `{}`", value));
+	}
+	Ok(precision)
+}
+
+fn parse_short_war(value: &str) -> Result<ShortWar, String> {
+	match value {
+		"lose" => Ok(ShortWar::Lose),
+		"use-last" => Ok(ShortWar::UseLast),
+		"draw" => Ok(ShortWar::Draw),
+		_ => Err(format!("short-war must be `lose`, `use-last` or `draw`, not `{}`", value))
+	}
+}
+
+fn parse_winnings(value: &str) -> Result<Winnings, String> {
+	match value {
+		"shuffled" => Ok(Winnings::Shuffled),
+		"in-order" => Ok(Winnings::InOrder),
+		_ => Err(format!("winnings must be `shuffled` or `in-order`, not `{}`", value))
+	}
+}
+
+/// Whether Aces are high.
+fn parse_ace(value: &str) -> Result<bool, String> {
+	match value {
+		"high" => Ok(true),
+		"low" => Ok(false),
+		_ => Err(format!("ace must be `high` or `low`, not `{}`", value))
+	}
+}
+
+fn parse_measure(value: &str) -> Result<Measure, String> {
+	match value {
+		"interval" => Ok(Measure::Interval),
+		"cumulative" => Ok(Measure::Cumulative),
+		_ => Err(format!("measure must be `interval` or `cumulative`, not `{}`", value))
+	}
+}
+
+fn parse_interval(value: &str) -> Result<IntervalMethod, String> {
+	match value {
+		"batch-means" => Ok(IntervalMethod::BatchMeans),
+		"bootstrap" => Ok(IntervalMethod::Bootstrap),
+		"independent" => Ok(IntervalMethod::Independent),
+		_ => Err(format!("interval must be `batch-means`, `bootstrap` or `independent`, not `{}`", value))
+	}
+}
+
+fn parse_probability(name: &str, value: &str) -> Result<f64, String> {
+	let p: f64 = parse_number(name, value)?;
+	if !(p > 0.0 && p < 1.0) {
+		return Err(format!("{} must be between 0 and 1, not `{}`", name, value));
+	}
+	Ok(p)
+}
+
+fn parse_multiplier(value: &str) -> Result<f64, String> {
+	let multiplier: f64 = parse_number("multiplier", value)?;
+	if !(multiplier.is_finite() && multiplier > 0.0) {
+		return Err(format!("multiplier must be greater than 0, not `{}`", value));
+	}
+	Ok(multiplier)
+}
+
+fn parse_format(value: &str) -> Result<Format, String> {
+	match value {
+		"text" => Ok(Format::Text),
+		"json" => Ok(Format::Json),
+		_ => Err(format!("format must be `text` or `json`, not `{}`", value))
+	}
+}
+
+/// Priming may be skipped entirely, but there must be time to sample.
+fn parse_seconds(name: &str, value: &str, zero: bool) -> Result<f64, String> {
+	let seconds: f64 = parse_number(name, value)?;
+	if !seconds.is_finite() || seconds < 0.0 || (seconds == 0.0 && !zero) || seconds > MAX_SECONDS {
+		return Err(format!("{} must be {} 0 and at most {}, not `{}`", name, if zero {"at least"} else {"greater than"}, MAX_SECONDS, value));
+	}
+	Ok(seconds)
+}
+
+fn parse_frequency(name: &str, value: &str) -> Result<f64, String> {
+	let hz: f64 = parse_number(name, value)?;
+	if !(hz.is_finite() && hz > 0.0 && hz <= NS) {
+		return Err(format!("{} must be greater than 0 and at most {}, not `{}`", name, NS, value));
+	}
+	Ok(hz)
+}
+
+/// The priming and sampling time, in seconds, must be short enough to time,
+/// and the samples taken at `hz` few enough to keep.
+fn check_run_time(prime: f64, sample: f64, hz: f64) -> Result<(), String> {
+	let seconds = prime + sample;
+	if seconds > MAX_SECONDS {
+		return Err(format!("priming and sampling may last at most {} seconds in all", MAX_SECONDS));
+	}
+	if seconds * hz > MAX_SAMPLES {
+		return Err(format!("a run may take at most {} samples, not {:.0}; lower the sample frequency or the run time", MAX_SAMPLES, seconds * hz));
+	}
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn args(line: &str) -> Vec<String> {
+		line.split_whitespace().map(|a| a.to_string()).collect()
+	}
+
+	fn error(line: &str) -> String {
+		match parse(&args(line)) {
+			Err(e) => e,
+			Ok(_) => panic!("`{}` should not parse", line)
+		}
+	}
+
+	#[test]
+	fn unknown_options_are_reported_as_unknown() {
+		assert_eq!(error("--sed 3"), "unknown option `--sed`");
+		assert_eq!(error("stats --sed 3"), "unknown option `--sed`");
+		assert_eq!(error("compare --sed 3 a b"), "unknown option `--sed`");
+		assert_eq!(error("--seed"), "--seed requires a value");
+	}
+
+	#[test]
+	fn flags_take_no_value() {
+		assert_eq!(error("--save-samples=1"), "--save-samples does not take a value, not `1`");
+		assert_eq!(error("stats --detect-cycles=yes"), "--detect-cycles does not take a value, not `yes`");
+	}
+
+	#[test]
+	fn every_listed_option_is_known() {
+		let known = |line: String| match parse(&args(&line)) {
+			Err(e) => !e.starts_with("unknown option"),
+			Ok(_) => true
+		};
+		for &name in RUN_OPTIONS.iter().chain(RULES_OPTIONS.iter()) {
+			assert!(known(format!("{} ?", name)), "{}", name);
+		}
+		for &name in STATS_OPTIONS.iter().chain(RULES_OPTIONS.iter()) {
+			assert!(known(format!("stats {} ?", name)), "{}", name);
+		}
+		for &name in COMPARE_OPTIONS.iter() {
+			assert!(known(format!("compare {} ?", name)), "{}", name);
+		}
+	}
+
+	#[test]
+	fn rules_options_set_the_rules() {
+		match parse(&args("stats --players 3 --deck piquet --decks 2 --jokers 1 --winnings in-order --detect-cycles --max-turns 50")) {
+			Ok(Command::Stats(options)) => {
+				assert_eq!(options.rules.players, 3);
+				assert!(options.rules.deck == DeckSpec {decks: 2, jokers: 1, ..DeckSpec::piquet()});
+				assert!(options.rules.detect_cycles);
+				assert_eq!(options.rules.max_turns, Some(50));
+			},
+			_ => panic!("stats options should parse")
+		}
+		assert_eq!(error("stats --detect-cycles"), "--detect-cycles needs --winnings in-order");
+		assert_eq!(error("stats --deck A:1 --players 3"), "a deck of 1 cards is too few for 3 players");
+	}
+
+	#[test]
+	fn run_times_are_bounded() {
+		assert_eq!(error("--prime-secs 1e300"), "--prime-secs must be at least 0 and at most 1000000, not `1e300`");
+		assert_eq!(error("--sample-secs 0"), "--sample-secs must be greater than 0 and at most 1000000, not `0`");
+		assert_eq!(error("--prime-secs 600000 --sample-secs 600000"), "priming and sampling may last at most 1000000 seconds in all");
+		assert_eq!(error("-m 1e300"), "priming and sampling may last at most 1000000 seconds in all");
+		assert_eq!(error("--prime-secs 0 --sample-secs 100000"), "a run may take at most 10000000 samples, not 20000000; lower the sample frequency or the run time");
+		assert_eq!(error("--sample-frequency 2e9"), "--sample-frequency must be greater than 0 and at most 1000000000, not `2e9`");
+		assert!(parse(&args("--prime-secs 0 --sample-secs 1000 --sample-frequency 10000")).is_ok());
+	}
+}
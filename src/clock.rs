@@ -0,0 +1,36 @@
+//! CPU time, where the platform offers it.
+
+/// CPU time used by the whole process, in nanoseconds.
+pub fn process_cpu_time() -> Option<u64> {
+    cpu_time(CPU_PROCESS)
+}
+
+/// CPU time used by the calling thread, in nanoseconds.
+pub fn thread_cpu_time() -> Option<u64> {
+    cpu_time(CPU_THREAD)
+}
+
+#[cfg(unix)]
+const CPU_PROCESS: ::libc::clockid_t = ::libc::CLOCK_PROCESS_CPUTIME_ID;
+#[cfg(unix)]
+const CPU_THREAD: ::libc::clockid_t = ::libc::CLOCK_THREAD_CPUTIME_ID;
+
+#[cfg(unix)]
+fn cpu_time(clock: ::libc::clockid_t) -> Option<u64> {
+    let mut ts = ::libc::timespec {tv_sec: 0, tv_nsec: 0};
+    let r = unsafe { ::libc::clock_gettime(clock, &mut ts) };
+    if r != 0 {
+        return None;
+    }
+    Some(ts.tv_sec as u64 * 1000000000 + ts.tv_nsec as u64)
+}
+
+#[cfg(not(unix))]
+const CPU_PROCESS: i32 = 0;
+#[cfg(not(unix))]
+const CPU_THREAD: i32 = 1;
+
+#[cfg(not(unix))]
+fn cpu_time(_: i32) -> Option<u64> {
+    None
+}
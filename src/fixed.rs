@@ -0,0 +1,247 @@
+//! The fixed-work benchmark.
+//!
+//! Rather than counting games played within a set time, a set number
+//! of games is shared between the threads and the time to finish them is measured.
+//! This suits comparing compilers and allocators, where the work must be identical.
+
+use std::fmt;
+
+use std::sync::{Arc, Barrier};
+use std::thread;
+
+use time::precise_time_ns;
+
+use rand;
+use rand::Rng;
+
+use wg;
+use wg::Rules;
+
+use clock;
+use host::HostInfo;
+use json;
+
+const MS:u64 = 1000000;
+const NS:u64 = 1000000000;
+
+/// How one thread did in a fixed-work run.
+#[derive(Clone, Copy)]
+pub struct ThreadTiming {
+    pub games: u64,
+    /// Nanoseconds from the common start until this thread finished.
+    pub elapsed_time: u64,
+    /// CPU time this thread used, in nanoseconds, where the platform offers it.
+    pub cpu_time: Option<u64>,
+}
+
+/// The results of a fixed-work run.
+#[derive(Clone)]
+pub struct FixedReport {
+    pub threads: usize,
+    pub games: u64,
+    pub seed: Option<u64>,
+    /// The rules the games were played by.
+    pub rules: Rules,
+    /// Wall-clock nanoseconds until the last thread finished.
+    pub elapsed_time: u64,
+    /// CPU time the whole process used meanwhile, in nanoseconds.
+    pub cpu_time: Option<u64>,
+    pub per_thread: Vec<ThreadTiming>,
+    /// Where the run was made, and how the binary was built.
+    pub host: HostInfo,
+}
+
+impl FixedReport {
+
+    /// Games per millisecond of wall-clock time.
+    pub fn speed(&self) -> f64 {
+        self.games as f64 / self.elapsed_time as f64 * MS as f64
+    }
+
+    /// Wall-clock nanoseconds per game, over all threads together.
+    pub fn ns_per_game(&self) -> f64 {
+        self.elapsed_time as f64 / self.games as f64
+    }
+
+    /// CPU nanoseconds per game.
+    pub fn cpu_ns_per_game(&self) -> Option<f64> {
+        self.cpu_time.map(|c| c as f64 / self.games as f64)
+    }
+
+    /// Nanoseconds between the first and the last thread finishing;
+    /// with fewer games than threads, the threads given none are left out.
+    pub fn skew(&self) -> u64 {
+        let busy = || self.per_thread.iter().filter(|t| t.games > 0).map(|t| t.elapsed_time);
+        let first = busy().min().unwrap_or(0);
+        let last = busy().max().unwrap_or(0);
+        last - first
+    }
+
+    /// The skew as a fraction of the whole run.
+    pub fn relative_skew(&self) -> f64 {
+        self.skew() as f64 / self.elapsed_time as f64
+    }
+
+    /// Renders the report as a single JSON document.
+    ///
+    /// Durations are in seconds and speeds in games per millisecond.
+    pub fn to_json(&self) -> String {
+        let threads: Vec<String> = self.per_thread.iter().map(|t| {
+            format!("{{\"games\": {}, \"elapsed_time\": {}, \"cpu_time\": {}}}",
+                t.games,
+                json::number(seconds(t.elapsed_time)),
+                json::optional(t.cpu_time.map(seconds))
+            )
+        }).collect();
+
+        let mut out = String::new();
+        out.push_str("{\n");
+        out.push_str(&format!("  \"threads\": {},\n", self.threads));
+        out.push_str(&format!("  \"games\": {},\n", self.games));
+        out.push_str(&format!("  \"seed\": {},\n", json::optional(self.seed)));
+        out.push_str(&format!("  \"rules\": {},\n", self.rules.to_json()));
+        out.push_str(&format!("  \"elapsed_time\": {},\n", json::number(seconds(self.elapsed_time))));
+        out.push_str(&format!("  \"cpu_time\": {},\n", json::optional(self.cpu_time.map(seconds))));
+        out.push_str(&format!("  \"speed\": {},\n", json::number(self.speed())));
+        out.push_str(&format!("  \"ns_per_game\": {},\n", json::number(self.ns_per_game())));
+        out.push_str(&format!("  \"cpu_ns_per_game\": {},\n", json::optional(self.cpu_ns_per_game())));
+        out.push_str(&format!("  \"skew\": {},\n", json::number(seconds(self.skew()))));
+        out.push_str(&format!("  \"relative_skew\": {},\n", json::number(self.relative_skew())));
+        out.push_str(&format!("  \"per_thread\": [{}],\n", threads.join(", ")));
+        out.push_str(&self.host.to_json_members());
+        out.push('\n');
+        out.push('}');
+        out
+    }
+
+}
+
+/// Displays the report as a text results block.
+impl fmt::Display for FixedReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Games: {}", self.games)?;
+        writeln!(f, "Threads: {}", self.threads)?;
+        writeln!(f, "Rules: {}", self.rules)?;
+        writeln!(f, "Wall Time: {:.3}s", seconds(self.elapsed_time))?;
+        match self.cpu_time {
+            Some(c) => writeln!(f, "CPU Time: {:.3}s", seconds(c))?,
+            None => writeln!(f, "CPU Time: unavailable")?
+        }
+        writeln!(f, "---")?;
+        writeln!(f, "Speed: {:.5} g/ms", self.speed())?;
+        writeln!(f, "Wall: {:.1} ns/g", self.ns_per_game())?;
+        if let Some(c) = self.cpu_ns_per_game() {
+            writeln!(f, "CPU: {:.1} ns/g", c)?;
+        }
+        writeln!(f, "---")?;
+        for (i, t) in self.per_thread.iter().enumerate() {
+            write!(f, "Thread {}: {} games in {:.3}s", i, t.games, seconds(t.elapsed_time))?;
+            if let Some(c) = t.cpu_time {
+                write!(f, " (cpu {:.3}s)", seconds(c))?;
+            }
+            writeln!(f)?;
+        }
+        writeln!(f, "Skew: {:.3}s ({:.2}%)", seconds(self.skew()), self.relative_skew() * 100.0)?;
+        writeln!(f, "---")?;
+        write!(f, "{}", self.host)
+    }
+}
+
+/// Plays exactly `games` games, shared between `threads` threads, and times them.
+///
+/// Every thread is started before the clock is; the run ends when the last thread finishes.
+/// With a seed, thread `i` uses `seed + i`, the same as the benchmark workers.
+pub fn run(games: u64, threads: usize, seed: Option<u64>, rules: &Rules) -> FixedReport {
+    let mut host = HostInfo::collect();
+
+    // the threads and this one wait here, so all start together
+    let barrier = Arc::new(Barrier::new(threads + 1));
+
+    let mut handles = Vec::with_capacity(threads);
+    for i in 0..threads {
+        // the remainder is spread over the first threads
+        let share = games / threads as u64 + if (i as u64) < games % threads as u64 {1} else {0};
+        let barrier = barrier.clone();
+        let rules = rules.clone();
+
+        handles.push(thread::spawn(move || {
+            barrier.wait();
+            let start_cpu = clock::thread_cpu_time();
+
+            match seed {
+                Some(seed) => play(share, &rules, &mut wg::seeded_rng(seed.wrapping_add(i as u64))),
+                None => play(share, &rules, &mut rand::thread_rng())
+            }
+
+            let cpu_time = match (start_cpu, clock::thread_cpu_time()) {
+                (Some(s), Some(e)) => Some(e - s),
+                _ => None
+            };
+            (share, precise_time_ns(), cpu_time)
+        }));
+    }
+
+    let start_cpu = clock::process_cpu_time();
+    barrier.wait();
+    let start_time = precise_time_ns();
+
+    let mut per_thread = Vec::with_capacity(threads);
+    for h in handles {
+        let (share, end_time, cpu_time) = h.join().unwrap();
+        per_thread.push(ThreadTiming {
+            games: share,
+            elapsed_time: end_time.saturating_sub(start_time),
+            cpu_time,
+        });
+    }
+
+    let end_time = precise_time_ns();
+    host.finish();
+    let cpu_time = match (start_cpu, clock::process_cpu_time()) {
+        (Some(s), Some(e)) => Some(e - s),
+        _ => None
+    };
+
+    FixedReport {
+        threads,
+        games,
+        seed,
+        rules: rules.clone(),
+        elapsed_time: end_time - start_time,
+        cpu_time,
+        per_thread,
+        host,
+    }
+}
+
+fn play<R: Rng>(games: u64, rules: &Rules, rng: &mut R) {
+    for _ in 0..games {
+        wg::play(rng, rules);
+    }
+}
+
+fn seconds(ns: u64) -> f64 {
+    ns as f64 / NS as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_game_is_played_once() {
+        let report = run(10, 3, Some(1), &Rules::new());
+        let shares: Vec<u64> = report.per_thread.iter().map(|t| t.games).collect();
+        assert_eq!(shares, vec![4, 3, 3]);
+        assert_eq!(report.games, 10);
+        assert!(report.per_thread.iter().all(|t| t.elapsed_time <= report.elapsed_time));
+        assert!(report.skew() <= report.elapsed_time);
+    }
+
+    #[test]
+    fn threads_without_games_are_left_out_of_the_skew() {
+        let report = run(2, 4, Some(1), &Rules::new());
+        let busy: Vec<u64> = report.per_thread[..2].iter().map(|t| t.elapsed_time).collect();
+        assert_eq!(report.skew(), busy.iter().max().unwrap() - busy.iter().min().unwrap());
+    }
+}
@@ -0,0 +1,371 @@
+//! A local history of benchmark results, and comparison against a baseline.
+//!
+//! The history is a JSON lines file: each run is appended as its
+//! JSON document (see `BenchmarkReport::to_json`) on a single line.
+//! A run can be loaded back from such a file, or from a file holding
+//! a single document, and compared with another.
+//!
+//! Runs saved with their speed samples are also compared by
+//! Welch's t-test and the Mann-Whitney U test. Each sample is much like the
+//! last, so the tests are made on batch means, which are close to independent;
+//! on the samples themselves, the p-values would be far too small.
+//!
+//! Only runs made alike can be compared: with the same thread count, rules,
+//! measure, interval method, confidence level and schema version.
+
+use std::fmt;
+
+use std::fs::{File, OpenOptions};
+use std::io::{self, Write};
+
+use report::{percent, BenchmarkReport};
+
+use host::read_file;
+use json;
+use json::Value;
+use significance;
+use interval;
+use significance::{MannWhitneyTest, WelchTest};
+
+/// The figures of a saved run that a comparison needs.
+///
+/// Speeds here are in games per millisecond, as they are saved.
+#[derive(Clone)]
+pub struct SavedRun {
+    /// Where the run was loaded from.
+    pub source: String,
+    pub threads: usize,
+    pub mean: f64,
+    pub ci_lower: f64,
+    pub ci_upper: f64,
+    pub speed: f64,
+    /// The speed samples, when the run was saved with them.
+    pub samples: Option<Vec<f64>>,
+    pub schema_version: u64,
+    /// The speed sampled, `cumulative` before schema version 3.
+    pub measure: String,
+    /// How the confidence interval was found, `independent` before schema version 2.
+    pub interval: String,
+    /// The confidence level of the interval, 99.9% when it was not saved.
+    pub confidence: f64,
+    /// The rules the games were played by, as saved; `None` when the run
+    /// was saved before there were rule variants, and so by the classic rules.
+    pub rules: Option<Value>,
+}
+
+/// Opens a history file for appending, creating the file if need be.
+pub fn open(path: &str) -> io::Result<File> {
+    OpenOptions::new().create(true).append(true).open(path)
+}
+
+/// Appends a run to a history file opened by `open`,
+/// with its speed samples when `samples` is set.
+pub fn append<W: Write>(file: &mut W, report: &BenchmarkReport, samples: bool) -> io::Result<()> {
+    let document = if samples {report.to_json_with_samples()} else {report.to_json()};
+    writeln!(file, "{}", json::compact(&document))
+}
+
+/// Loads a saved run.
+///
+/// `spec` is a path, optionally followed by `:n` to pick the `n`th run of a
+/// history file, counting from 1; negative `n` counts back from the last run.
+/// Without `:n`, the last run is used.
+pub fn load(spec: &str) -> Result<SavedRun, String> {
+    let (path, index) = spec.rsplit_once(':')
+        .and_then(|(path, n)| n.parse::<i64>().ok().map(|n| (path, n)))
+        .unwrap_or((spec, -1));
+
+    let text = read_file(path).ok_or(format!("could not read {}", path))?;
+
+    // a file holding one document, such as the output of `--format json`,
+    // is a history of a single run
+    let runs: Vec<Value> = match json::parse(&text) {
+        Ok(document) => vec![document],
+        Err(_) => {
+            let mut runs = Vec::new();
+            for (n, line) in text.lines().enumerate().filter(|&(_, l)| !l.trim().is_empty()) {
+                runs.push(json::parse(line).map_err(|e| format!("{} line {}: {}", path, n + 1, e))?);
+            }
+            runs
+        }
+    };
+
+    let chosen = if index > 0 {
+        runs.get(index as usize - 1)
+    } else if index < 0 && ((-index) as usize) <= runs.len() {
+        runs.get(runs.len() - (-index) as usize)
+    } else {
+        None
+    };
+    let run = chosen.ok_or(format!("{} has no run {} (it holds {})", path, index, runs.len()))?;
+
+    let number = |keys: &[&str]| -> Result<f64, String> {
+        run.path(keys).and_then(|v| v.as_f64()).ok_or(format!("{} has no `{}`", spec, keys.join(".")))
+    };
+    // the interval was `ci_99_9` before schema version 2
+    let ci = if run.path(&["statistics", "ci"]).is_some() {"ci"} else {"ci_99_9"};
+    let setting = |key: &str, before: &str| -> String {
+        match run.path(&["settings", key]) {
+            Some(Value::String(s)) => s.clone(),
+            _ => before.to_string()
+        }
+    };
+
+    Ok(SavedRun {
+        source: spec.to_string(),
+        threads: number(&["settings", "threads"])? as usize,
+        mean: number(&["statistics", "mean"])?,
+        ci_lower: number(&["statistics", ci, "lower"])?,
+        ci_upper: number(&["statistics", ci, "upper"])?,
+        speed: number(&["speed"])?,
+        samples: run.get("speed_samples")
+            .and_then(|v| v.as_array())
+            .and_then(|a| a.iter().map(|s| s.as_f64()).collect()),
+        schema_version: number(&["schema_version"])? as u64,
+        measure: setting("measure", "cumulative"),
+        interval: setting("interval", "independent"),
+        confidence: run.path(&["settings", "confidence"]).and_then(|v| v.as_f64()).unwrap_or(0.999),
+        rules: run.path(&["settings", "rules"]).cloned(),
+    })
+}
+
+/// A current run measured against a baseline run.
+#[derive(Clone)]
+pub struct Comparison {
+    pub baseline: SavedRun,
+    pub current: SavedRun,
+    /// The largest drop in mean speed that still passes, as a fraction.
+    pub threshold: f64,
+    /// The p-value below which a difference is taken as real.
+    pub alpha: f64,
+    /// Present when both runs were saved with their samples;
+    /// made on the batch means of the samples (see `interval::batch_means`).
+    pub welch: Option<WelchTest>,
+    pub mann_whitney: Option<MannWhitneyTest>,
+}
+
+impl Comparison {
+
+    /// Compares two runs; they must have been made alike, see `differences`.
+    pub fn new(baseline: SavedRun, current: SavedRun, threshold: f64, alpha: f64) -> Result<Comparison, String> {
+        let differences = differences(&baseline, &current);
+        if !differences.is_empty() {
+            return Err(format!("{} and {} were not run alike: {}", baseline.source, current.source, differences.join("; ")));
+        }
+        let (welch, mann_whitney) = match (&baseline.samples, &current.samples) {
+            (Some(a), Some(b)) => {
+                let (a, b) = (interval::batch_means(a), interval::batch_means(b));
+                (significance::welch(&a, &b), significance::mann_whitney(&a, &b))
+            },
+            _ => (None, None)
+        };
+        Ok(Comparison {
+            baseline,
+            current,
+            threshold,
+            alpha,
+            welch,
+            mann_whitney,
+        })
+    }
+
+    /// The change in mean speed, in games per millisecond.
+    pub fn delta(&self) -> f64 {
+        self.current.mean - self.baseline.mean
+    }
+
+    /// The change in mean speed as a fraction of the baseline.
+    pub fn relative_delta(&self) -> f64 {
+        self.delta() / self.baseline.mean
+    }
+
+    /// Whether the confidence intervals of the two means overlap.
+    pub fn ci_overlap(&self) -> bool {
+        self.current.ci_lower <= self.baseline.ci_upper && self.baseline.ci_lower <= self.current.ci_upper
+    }
+
+    /// Whether the difference in speed is real: both tests fall below alpha
+    /// when the samples were saved, or else the confidence intervals do not overlap.
+    pub fn significant(&self) -> bool {
+        match (self.welch, self.mann_whitney) {
+            (Some(t), Some(u)) => t.p_value < self.alpha && u.p_value < self.alpha,
+            _ => !self.ci_overlap()
+        }
+    }
+
+    /// A run fails when its mean speed drops by more than the threshold,
+    /// and the drop is significant.
+    pub fn passed(&self) -> bool {
+        !(self.relative_delta() < -self.threshold && self.significant())
+    }
+
+    /// Renders the comparison as a single JSON document.
+    pub fn to_json(&self) -> String {
+        let mut out = String::new();
+        out.push_str("{\n");
+        out.push_str(&format!("  \"baseline\": {},\n", json_run(&self.baseline)));
+        out.push_str(&format!("  \"current\": {},\n", json_run(&self.current)));
+        out.push_str(&format!("  \"delta\": {},\n", json::number(self.delta())));
+        out.push_str(&format!("  \"relative_delta\": {},\n", json::number(self.relative_delta())));
+        out.push_str(&format!("  \"ci_overlap\": {},\n", self.ci_overlap()));
+        out.push_str(&format!("  \"welch\": {},\n", json::optional(self.welch.map(|t| {
+            format!("{{\"t\": {}, \"degrees_of_freedom\": {}, \"p_value\": {}, \"cohens_d\": {}}}",
+                json::number(t.t), json::number(t.degrees_of_freedom), json::number(t.p_value), json::number(t.cohens_d))
+        }))));
+        out.push_str(&format!("  \"mann_whitney\": {},\n", json::optional(self.mann_whitney.map(|u| {
+            format!("{{\"u\": {}, \"z\": {}, \"p_value\": {}, \"rank_biserial\": {}}}",
+                json::number(u.u), json::number(u.z), json::number(u.p_value), json::number(u.rank_biserial))
+        }))));
+        out.push_str(&format!("  \"alpha\": {},\n", json::number(self.alpha)));
+        out.push_str(&format!("  \"significant\": {},\n", self.significant()));
+        out.push_str(&format!("  \"threshold\": {},\n", json::number(self.threshold)));
+        out.push_str(&format!("  \"passed\": {}\n", self.passed()));
+        out.push('}');
+        out
+    }
+
+}
+
+/// Displays the comparison and its verdict.
+impl fmt::Display for Comparison {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for &(name, run) in [("Baseline", &self.baseline), ("Current", &self.current)].iter() {
+            writeln!(f, "{}:\t {:9.5} g/ms; CI < {:9.5} - {:9.5} >; threads = {}; {}",
+                name, run.mean, run.ci_lower, run.ci_upper, run.threads, run.source)?;
+        }
+        writeln!(f, "---")?;
+        writeln!(f, "Delta:\t {:+9.5} g/ms ({:+.2}%)", self.delta(), self.relative_delta() * 100.0)?;
+        writeln!(f, "CI Overlap: {}", if self.ci_overlap() {"yes"} else {"no"})?;
+        match self.welch {
+            Some(t) => writeln!(f, "Welch: t = {:+.3}; df = {:.1}; p = {:.3e}; d = {:+.3}",
+                t.t, t.degrees_of_freedom, t.p_value, t.cohens_d)?,
+            None => writeln!(f, "Welch: needs both runs saved with --save-samples")?
+        }
+        match self.mann_whitney {
+            Some(u) => writeln!(f, "Mann-Whitney: U = {:.0}; z = {:+.3}; p = {:.3e}; r = {:+.3}",
+                u.u, u.z, u.p_value, u.rank_biserial)?,
+            None => writeln!(f, "Mann-Whitney: needs both runs saved with --save-samples")?
+        }
+        writeln!(f, "Significant: {} (alpha = {})", if self.significant() {"yes"} else {"no"}, self.alpha)?;
+        writeln!(f, "Threshold: {:.2}%", self.threshold * 100.0)?;
+        writeln!(f, "---")?;
+        write!(f, "Verdict: {}", if self.passed() {"PASS"} else {"FAIL"})
+    }
+}
+
+/// The settings that differ between two runs, such that their speeds cannot be compared.
+pub fn differences(a: &SavedRun, b: &SavedRun) -> Vec<String> {
+    let mut differences = Vec::new();
+    if a.schema_version != b.schema_version {
+        differences.push(format!("schema version {} and {}", a.schema_version, b.schema_version));
+    }
+    if a.threads != b.threads {
+        differences.push(format!("threads {} and {}", a.threads, b.threads));
+    }
+    if a.measure != b.measure {
+        differences.push(format!("measure {} and {}", a.measure, b.measure));
+    }
+    if a.interval != b.interval {
+        differences.push(format!("interval {} and {}", a.interval, b.interval));
+    }
+    if a.confidence != b.confidence {
+        differences.push(format!("confidence {}% and {}%", percent(a.confidence), percent(b.confidence)));
+    }
+    if a.rules != b.rules {
+        differences.push("different rules".to_string());
+    }
+    differences
+}
+
+fn json_run(run: &SavedRun) -> String {
+    format!("{{\"source\": {}, \"threads\": {}, \"mean\": {}, \"ci_lower\": {}, \"ci_upper\": {}, \"speed\": {}, \"samples\": {}}}",
+        json::string(&run.source),
+        run.threads,
+        json::number(run.mean),
+        json::number(run.ci_lower),
+        json::number(run.ci_upper),
+        json::number(run.speed),
+        json::optional(run.samples.as_ref().map(|s| s.len()))
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::fs;
+    use std::process;
+
+
+    #[test]
+    fn runs_are_loaded_by_position() {
+        let path = env::temp_dir().join(format!("wargame-history-test-{}.jsonl", process::id()));
+        let path = path.to_str().unwrap().to_string();
+        let document = |speed: f64| format!("{{\"schema_version\": 1, \"settings\": {{\"threads\": 2}}, \"statistics\": {{\"mean\": {}, \"ci_99_9\": {{\"lower\": 0.5, \"upper\": 1.5}}}}, \"speed\": {}}}", speed, speed);
+        let speed = |spec: String| load(&spec).map(|run| run.speed);
+
+        fs::write(&path, format!("{}\n{}\n\n{}\n", document(1.0), document(2.0), document(3.0))).unwrap();
+        // the last run, unless told otherwise
+        assert_eq!(speed(path.clone()), Ok(3.0));
+        assert_eq!(speed(format!("{}:1", path)), Ok(1.0));
+        assert_eq!(speed(format!("{}:-2", path)), Ok(2.0));
+        assert_eq!(speed(format!("{}:-3", path)), Ok(1.0));
+        for n in ["0", "4", "-4"].iter() {
+            assert!(load(&format!("{}:{}", path, n)).is_err(), "{}", n);
+        }
+
+        // a document spread over several lines is a history of one run
+        fs::write(&path, document(4.0).replace(", ", ",\n  ")).unwrap();
+        assert_eq!(speed(path.clone()), Ok(4.0));
+        assert!(load(&format!("{}:2", path)).is_err());
+
+        fs::remove_file(&path).unwrap();
+        assert!(load(&path).is_err());
+    }
+
+    fn run(source: &str, mean: f64) -> SavedRun {
+        SavedRun {
+            source: source.to_string(),
+            threads: 1,
+            mean,
+            ci_lower: mean - 0.01,
+            ci_upper: mean + 0.01,
+            speed: mean,
+            samples: None,
+            schema_version: 3,
+            measure: "interval".to_string(),
+            interval: "batch-means".to_string(),
+            confidence: 0.999,
+            rules: None,
+        }
+    }
+
+    #[test]
+    fn runs_made_differently_are_refused() {
+        let mut other = run("b", 1.0);
+        other.threads = 4;
+        other.measure = "cumulative".to_string();
+        assert_eq!(differences(&run("a", 1.0), &other), vec!["threads 1 and 4", "measure interval and cumulative"]);
+        assert!(Comparison::new(run("a", 1.0), other, 0.05, 0.001).is_err());
+
+        let mut old = run("b", 1.0);
+        old.schema_version = 2;
+        assert!(Comparison::new(run("a", 1.0), old, 0.05, 0.001).is_err());
+
+        let mut wider = run("b", 1.0);
+        wider.confidence = 0.95;
+        assert_eq!(differences(&run("a", 1.0), &wider), vec!["confidence 99.9% and 95%"]);
+    }
+
+    #[test]
+    fn a_significant_drop_beyond_the_threshold_fails() {
+        let comparison = Comparison::new(run("a", 1.0), run("b", 0.9), 0.05, 0.001).unwrap();
+        assert!((comparison.relative_delta() + 0.1).abs() < 1e-12);
+        assert!(!comparison.ci_overlap());
+        assert!(!comparison.passed());
+
+        // within the threshold
+        let comparison = Comparison::new(run("a", 1.0), run("b", 0.97), 0.05, 0.001).unwrap();
+        assert!(comparison.passed());
+    }
+}
@@ -0,0 +1,165 @@
+//! Where a run was made, and how the binary was built.
+//!
+//! Scores are only comparable between like machines and like builds,
+//! so every report carries this with it.
+//! The host details are read from `/proc` and are missing elsewhere.
+
+use std::fmt;
+
+use std::collections::HashSet;
+use std::fs::File;
+use std::io::Read;
+
+use affinity;
+use json;
+
+/// The machine a run was made on, and the build it was made with.
+#[derive(Clone)]
+pub struct HostInfo {
+    pub cpu_model: Option<String>,
+    /// Physical cores.
+    pub cores: Option<usize>,
+    /// Logical CPUs online, hyperthreads included.
+    pub threads: usize,
+    /// The logical CPUs this process may run on; fewer than `threads`
+    /// under `taskset` or a cgroup cpuset.
+    pub allowed_cpus: usize,
+    pub kernel: Option<String>,
+    /// The 1, 5 and 15 minute load averages when the run started.
+    pub load_start: Option<[f64; 3]>,
+    /// The same when the run ended.
+    pub load_end: Option<[f64; 3]>,
+
+    pub crate_version: String,
+    pub rustc_version: String,
+    /// `debug` or `release`.
+    pub profile: String,
+    pub target: String,
+}
+
+impl HostInfo {
+
+    /// Describes this machine and build; call when the run starts.
+    pub fn collect() -> HostInfo {
+        let cpuinfo = read_file("/proc/cpuinfo").unwrap_or_default();
+
+        HostInfo {
+            cpu_model: cpu_model(&cpuinfo),
+            cores: cores(&cpuinfo),
+            threads: affinity::online_cpu_count(),
+            allowed_cpus: affinity::cpu_count(),
+            kernel: read_file("/proc/sys/kernel/osrelease").map(|k| k.trim().to_string()),
+            load_start: load_average(),
+            load_end: None,
+
+            crate_version: env!("CARGO_PKG_VERSION").to_string(),
+            rustc_version: env!("WARGAME_RUSTC_VERSION").to_string(),
+            profile: env!("WARGAME_PROFILE").to_string(),
+            target: env!("WARGAME_TARGET").to_string(),
+        }
+    }
+
+    /// Records the load average; call when the run ends.
+    pub fn finish(&mut self) {
+        self.load_end = load_average();
+    }
+
+    /// Renders the host and build as two JSON members, `"host"` and `"build"`,
+    /// for inclusion in a larger document.
+    pub fn to_json_members(&self) -> String {
+        let mut out = String::new();
+        out.push_str("  \"host\": {\n");
+        out.push_str(&format!("    \"cpu_model\": {},\n", json::optional(self.cpu_model.as_ref().map(|m| json::string(m)))));
+        out.push_str(&format!("    \"cores\": {},\n", json::optional(self.cores)));
+        out.push_str(&format!("    \"threads\": {},\n", self.threads));
+        out.push_str(&format!("    \"allowed_cpus\": {},\n", self.allowed_cpus));
+        out.push_str(&format!("    \"kernel\": {},\n", json::optional(self.kernel.as_ref().map(|k| json::string(k)))));
+        out.push_str(&format!("    \"load_start\": {},\n", json::optional(self.load_start.map(json_load))));
+        out.push_str(&format!("    \"load_end\": {}\n", json::optional(self.load_end.map(json_load))));
+        out.push_str("  },\n");
+        out.push_str("  \"build\": {\n");
+        out.push_str(&format!("    \"crate_version\": {},\n", json::string(&self.crate_version)));
+        out.push_str(&format!("    \"rustc_version\": {},\n", json::string(&self.rustc_version)));
+        out.push_str(&format!("    \"profile\": {},\n", json::string(&self.profile)));
+        out.push_str(&format!("    \"target\": {}\n", json::string(&self.target)));
+        out.push_str("  }");
+        out
+    }
+
+}
+
+/// Displays the host and build as a text block.
+impl fmt::Display for HostInfo {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "CPU: {}", self.cpu_model.as_ref().map_or("unknown", |m| m.as_str()))?;
+        match self.cores {
+            Some(c) => write!(f, "Cores: {} physical; {} logical", c, self.threads)?,
+            None => write!(f, "Cores: {} logical", self.threads)?
+        }
+        writeln!(f, "; {} allowed", self.allowed_cpus)?;
+        writeln!(f, "Kernel: {}", self.kernel.as_ref().map_or("unknown", |k| k.as_str()))?;
+        writeln!(f, "Load: {} -> {}", text_load(self.load_start), text_load(self.load_end))?;
+        write!(f, "Build: wargame-rust {}; {}; {}; {}",
+            self.crate_version, self.rustc_version, self.profile, self.target)
+    }
+}
+
+/// Reads a whole file, or nothing if it cannot be read.
+pub(crate) fn read_file(path: &str) -> Option<String> {
+    let mut s = String::new();
+    match File::open(path).and_then(|mut f| f.read_to_string(&mut s)) {
+        Ok(_) => Some(s),
+        Err(_) => None
+    }
+}
+
+fn cpu_model(cpuinfo: &str) -> Option<String> {
+    cpuinfo.lines()
+        .find(|l| l.starts_with("model name"))
+        .and_then(|l| l.split_once(':'))
+        .map(|(_, m)| m.trim().to_string())
+}
+
+/// Counts the distinct `(physical id, core id)` pairs.
+fn cores(cpuinfo: &str) -> Option<usize> {
+    let mut cores = HashSet::new();
+    let mut package = None;
+    for line in cpuinfo.lines() {
+        let mut parts = line.splitn(2, ':');
+        let key = parts.next().unwrap_or("").trim();
+        let value = parts.next().unwrap_or("").trim();
+        match key {
+            "physical id" => package = Some(value.to_string()),
+            "core id" => {
+                cores.insert((package.clone(), value.to_string()));
+            },
+            _ => {}
+        }
+    }
+    if cores.is_empty() {
+        None
+    } else {
+        Some(cores.len())
+    }
+}
+
+fn load_average() -> Option<[f64; 3]> {
+    let loadavg = read_file("/proc/loadavg")?;
+    let values: Vec<f64> = loadavg.split_whitespace().take(3).filter_map(|v| v.parse().ok()).collect();
+    if values.len() == 3 {
+        Some([values[0], values[1], values[2]])
+    } else {
+        None
+    }
+}
+
+fn text_load(load: Option<[f64; 3]>) -> String {
+    match load {
+        Some(l) => format!("{:.2} {:.2} {:.2}", l[0], l[1], l[2]),
+        None => "unknown".to_string()
+    }
+}
+
+fn json_load(load: [f64; 3]) -> String {
+    format!("[{}, {}, {}]", json::number(load[0]), json::number(load[1]), json::number(load[2]))
+}
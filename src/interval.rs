@@ -0,0 +1,203 @@
+//! Confidence intervals for the mean speed.
+//!
+//! The speed samples are taken a few milliseconds apart, so each is much
+//! like the last, and treating them as independent makes the interval far
+//! too narrow. Batch means and the block bootstrap both allow for this,
+//! and the effective sample size shows how much it matters.
+
+use std::fmt;
+
+use rand;
+use rand::Rng;
+
+use wg;
+
+use benchmark::{get_mean, get_standard_deviation};
+use significance::t_critical;
+
+/// 99.9%
+pub const CONFIDENCE:f64 = 0.999;
+
+/// Resamples drawn by the block bootstrap.
+pub const BOOTSTRAP_RESAMPLES:usize = 1000;
+
+/// How the confidence interval of the mean is found.
+#[derive(Clone, Copy, PartialEq)]
+pub enum IntervalMethod {
+    /// Student's t, as if every sample were independent.
+    Independent,
+    /// Student's t over the means of `√n` consecutive batches.
+    BatchMeans,
+    /// Percentiles of the means of moving block bootstrap resamples,
+    /// with blocks as long as the autocorrelation time.
+    Bootstrap,
+}
+
+/// Displays the method as it is given on the command line.
+impl fmt::Display for IntervalMethod {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            IntervalMethod::Independent => write!(f, "independent"),
+            IntervalMethod::BatchMeans => write!(f, "batch-means"),
+            IntervalMethod::Bootstrap => write!(f, "bootstrap"),
+        }
+    }
+}
+
+/// The confidence interval of the mean of `samples`, as `(lower, upper)`.
+///
+/// `seed` seeds the bootstrap, so a seeded run gives the same interval.
+/// There is no interval, only NaN, with fewer than two samples.
+pub fn confidence_interval(samples: &[f64], method: IntervalMethod, confidence: f64, seed: Option<u64>) -> (f64, f64) {
+    if samples.len() < 2 {
+        return (f64::NAN, f64::NAN);
+    }
+    match method {
+        IntervalMethod::Independent => t_interval(samples, confidence),
+        IntervalMethod::BatchMeans => t_interval(&batch_means(samples), confidence),
+        IntervalMethod::Bootstrap => match seed {
+            Some(seed) => bootstrap(samples, confidence, &mut wg::seeded_rng(seed)),
+            None => bootstrap(samples, confidence, &mut rand::thread_rng())
+        }
+    }
+}
+
+/// The means of `√n` consecutive batches of the samples, and at least two;
+/// batches this long are close to independent, though the samples are not.
+/// Every sample falls in a batch: when they do not divide evenly,
+/// the batches differ in length by at most one.
+///
+/// With fewer than two samples there are no batches.
+pub fn batch_means(samples: &[f64]) -> Vec<f64> {
+    let n = samples.len();
+    if n < 2 {
+        return Vec::new();
+    }
+    let batches = ((n as f64).sqrt() as usize).max(2);
+    (0..batches).map(|i| get_mean(&samples[i * n / batches..(i + 1) * n / batches])).collect()
+}
+
+/// How many independent samples `samples` are worth: `n / (1 + 2 Σ ρ_k)`,
+/// summing the autocorrelations `ρ_k` until the first that is not positive.
+pub fn effective_sample_size(samples: &[f64]) -> f64 {
+    let n = samples.len() as f64;
+    n / autocorrelation_time(samples)
+}
+
+/// `1 + 2 Σ ρ_k`, the number of samples it takes for one to be forgotten.
+fn autocorrelation_time(samples: &[f64]) -> f64 {
+    let mean = get_mean(samples);
+    let deviations: Vec<f64> = samples.iter().map(|s| s - mean).collect();
+    let variance: f64 = deviations.iter().map(|d| d * d).sum();
+    if variance.is_nan() || variance <= 0.0 {
+        return 1.0;
+    }
+
+    let mut sum = 0f64;
+    for k in 1..deviations.len() {
+        let covariance: f64 = deviations.iter().zip(deviations[k..].iter()).map(|(a, b)| a * b).sum();
+        let rho = covariance / variance;
+        if rho <= 0.0 {
+            break;
+        }
+        sum += rho;
+    }
+    1.0 + 2.0 * sum
+}
+
+fn t_interval(samples: &[f64], confidence: f64) -> (f64, f64) {
+    let mean = get_mean(samples);
+    let stdev = get_standard_deviation(samples, mean);
+    let margin = t_critical(confidence, (samples.len() - 1) as f64) * stdev / (samples.len() as f64).sqrt();
+    (mean - margin, mean + margin)
+}
+
+fn bootstrap<R: Rng>(samples: &[f64], confidence: f64, rng: &mut R) -> (f64, f64) {
+    let n = samples.len();
+    let block = (autocorrelation_time(samples).ceil() as usize).max(1).min(n);
+    let starts = n - block + 1;
+
+    let mut means = Vec::with_capacity(BOOTSTRAP_RESAMPLES);
+    for _ in 0..BOOTSTRAP_RESAMPLES {
+        let mut total = 0f64;
+        let mut count = 0;
+        while count < n {
+            let start = rng.gen_range(0, starts);
+            for s in samples[start..(start + block).min(start + n - count)].iter() {
+                total += *s;
+                count += 1;
+            }
+        }
+        means.push(total / n as f64);
+    }
+    means.sort_by(|a, b| a.partial_cmp(b).unwrap());
+
+    let tail = (1.0 - confidence) / 2.0;
+    let last = (BOOTSTRAP_RESAMPLES - 1) as f64;
+    (means[(tail * last).round() as usize], means[((1.0 - tail) * last).round() as usize])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn independent_interval_matches_reference() {
+        let samples: Vec<f64> = (1..11).map(|i| i as f64).collect();
+        let (lower, upper) = confidence_interval(&samples, IntervalMethod::Independent, 0.95, None);
+        assert!((lower - (5.5 - 2.165850589668169)).abs() < 1e-6);
+        assert!((upper - (5.5 + 2.165850589668169)).abs() < 1e-6);
+    }
+
+    #[test]
+    fn batch_means_interval_matches_reference() {
+        // four batches of four: means 2.5, 6.5, 10.5 and 14.5
+        let samples: Vec<f64> = (1..17).map(|i| i as f64).collect();
+        let (lower, upper) = confidence_interval(&samples, IntervalMethod::BatchMeans, 0.95, None);
+        let margin = t_critical(0.95, 3.0) * 5.163977794943222 / 2.0;
+        assert!((lower - (8.5 - margin)).abs() < 1e-9);
+        assert!((upper - (8.5 + margin)).abs() < 1e-9);
+    }
+
+    #[test]
+    fn too_few_samples_have_no_interval() {
+        for &method in [IntervalMethod::Independent, IntervalMethod::BatchMeans, IntervalMethod::Bootstrap].iter() {
+            let (lower, upper) = confidence_interval(&[1.0], method, 0.95, Some(1));
+            assert!(lower.is_nan() && upper.is_nan());
+        }
+    }
+
+    #[test]
+    fn seeded_bootstrap_is_repeatable_and_holds_the_mean() {
+        let samples: Vec<f64> = (0..200).map(|i| ((i * 37) % 101) as f64).collect();
+        let mean = get_mean(&samples);
+        let a = confidence_interval(&samples, IntervalMethod::Bootstrap, 0.95, Some(42));
+        let b = confidence_interval(&samples, IntervalMethod::Bootstrap, 0.95, Some(42));
+        assert!(a == b);
+        assert!(a.0 < mean && mean < a.1);
+    }
+
+    #[test]
+    fn effective_sample_size_allows_for_autocorrelation() {
+        // each sample is the opposite of the last: no positive autocorrelation
+        let alternating: Vec<f64> = (0..100).map(|i| if i % 2 == 0 {1.0} else {-1.0}).collect();
+        assert_eq!(effective_sample_size(&alternating), 100.0);
+        // nothing varies
+        assert_eq!(effective_sample_size(&[2.0; 10]), 10.0);
+        // a slow drift up and down is worth far fewer samples
+        let drifting: Vec<f64> = (0..400).map(|i| ((i / 40) % 2) as f64).collect();
+        assert!(effective_sample_size(&drifting) < 40.0);
+    }
+
+    #[test]
+    fn batch_means_take_root_n_batches() {
+        let samples: Vec<f64> = (1..17).map(|i| i as f64).collect();
+        assert_eq!(batch_means(&samples), vec![2.5, 6.5, 10.5, 14.5]);
+        // the remainder is spread across the batches, so no sample is left out
+        let samples: Vec<f64> = (1..19).map(|i| i as f64).collect();
+        assert_eq!(batch_means(&samples), vec![2.5, 7.0, 11.5, 16.0]);
+        // at least two batches
+        assert_eq!(batch_means(&[1.0, 3.0, 5.0]), vec![1.0, 4.0]);
+        assert!(batch_means(&[1.0]).is_empty());
+    }
+}
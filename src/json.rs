@@ -0,0 +1,305 @@
+//! Small helpers for writing JSON by hand.
+
+/// JSON has no representation for NaN or infinity, so those become `null`.
+pub fn number(f: f64) -> String {
+    if f.is_finite() {
+        f.to_string()
+    } else {
+        "null".to_string()
+    }
+}
+
+/// Quotes a string, escaping what JSON requires.
+pub fn string(s: &str) -> String {
+    let mut quoted = String::with_capacity(s.len() + 2);
+    quoted.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c if (c as u32) < 0x20 => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+            c => quoted.push(c)
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// Writes an optional value, or `null` when there is none.
+pub fn optional<T: ToString>(value: Option<T>) -> String {
+    match value {
+        Some(v) => v.to_string(),
+        None => "null".to_string()
+    }
+}
+
+/// Joins a document written by this crate onto a single line,
+/// as JSON lines files need.
+///
+/// Strings are written escaped, so every line break is layout.
+pub fn compact(document: &str) -> String {
+    let lines: Vec<&str> = document.lines().map(|l| l.trim_start()).collect();
+    lines.join(" ")
+}
+
+/// A parsed JSON value.
+#[derive(Clone, PartialEq)]
+pub enum Value {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Value>),
+    /// Members in the order they were written.
+    Object(Vec<(String, Value)>),
+}
+
+impl Value {
+
+    /// A member of an object.
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        match *self {
+            Value::Object(ref members) => members.iter().find(|m| m.0 == key).map(|m| &m.1),
+            _ => None
+        }
+    }
+
+    /// Follows a path of object members, such as `["statistics", "mean"]`.
+    pub fn path(&self, keys: &[&str]) -> Option<&Value> {
+        keys.iter().try_fold(self, |v, k| v.get(k))
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        match *self {
+            Value::Number(n) => Some(n),
+            _ => None
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&Vec<Value>> {
+        match *self {
+            Value::Array(ref a) => Some(a),
+            _ => None
+        }
+    }
+
+}
+
+/// Parses a single JSON document.
+pub fn parse(text: &str) -> Result<Value, String> {
+    let mut parser = Parser {chars: text.chars().collect(), i: 0};
+    let value = parser.value()?;
+    parser.whitespace();
+    if parser.i < parser.chars.len() {
+        return Err(format!("unexpected `{}` after the document", parser.chars[parser.i]));
+    }
+    Ok(value)
+}
+
+struct Parser {
+    chars: Vec<char>,
+    i: usize,
+}
+
+impl Parser {
+
+    fn whitespace(&mut self) {
+        while self.i < self.chars.len() && self.chars[self.i].is_whitespace() {
+            self.i += 1;
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.i).cloned()
+    }
+
+    fn expect(&mut self, c: char) -> Result<(), String> {
+        self.whitespace();
+        match self.peek() {
+            Some(p) if p == c => {
+                self.i += 1;
+                Ok(())
+            },
+            Some(p) => Err(format!("expected `{}`, found `{}`", c, p)),
+            None => Err(format!("expected `{}`, found the end", c))
+        }
+    }
+
+    fn literal(&mut self, word: &str, value: Value) -> Result<Value, String> {
+        for c in word.chars() {
+            if self.peek() != Some(c) {
+                return Err(format!("expected `{}`", word));
+            }
+            self.i += 1;
+        }
+        Ok(value)
+    }
+
+    fn value(&mut self) -> Result<Value, String> {
+        self.whitespace();
+        match self.peek() {
+            Some('{') => self.object(),
+            Some('[') => self.array(),
+            Some('"') => self.string().map(Value::String),
+            Some('t') => self.literal("true", Value::Bool(true)),
+            Some('f') => self.literal("false", Value::Bool(false)),
+            Some('n') => self.literal("null", Value::Null),
+            Some(c) if c == '-' || c.is_ascii_digit() => self.number(),
+            Some(c) => Err(format!("unexpected `{}`", c)),
+            None => Err("unexpected end of document".to_string())
+        }
+    }
+
+    fn object(&mut self) -> Result<Value, String> {
+        self.expect('{')?;
+        let mut members = Vec::new();
+        self.whitespace();
+        if self.peek() == Some('}') {
+            self.i += 1;
+            return Ok(Value::Object(members));
+        }
+        loop {
+            self.whitespace();
+            let key = self.string()?;
+            self.expect(':')?;
+            let value = self.value()?;
+            members.push((key, value));
+            self.whitespace();
+            match self.peek() {
+                Some(',') => self.i += 1,
+                _ => break
+            }
+        }
+        self.expect('}')?;
+        Ok(Value::Object(members))
+    }
+
+    fn array(&mut self) -> Result<Value, String> {
+        self.expect('[')?;
+        let mut items = Vec::new();
+        self.whitespace();
+        if self.peek() == Some(']') {
+            self.i += 1;
+            return Ok(Value::Array(items));
+        }
+        loop {
+            items.push(self.value()?);
+            self.whitespace();
+            match self.peek() {
+                Some(',') => self.i += 1,
+                _ => break
+            }
+        }
+        self.expect(']')?;
+        Ok(Value::Array(items))
+    }
+
+    fn string(&mut self) -> Result<String, String> {
+        self.expect('"')?;
+        let mut s = String::new();
+        loop {
+            let c = match self.peek() {
+                Some(c) => c,
+                None => return Err("unterminated string".to_string())
+            };
+            self.i += 1;
+            match c {
+                '"' => return Ok(s),
+                '\\' => {
+                    let e = self.peek().ok_or("unterminated string".to_string())?;
+                    self.i += 1;
+                    match e {
+                        'n' => s.push('\n'),
+                        'r' => s.push('\r'),
+                        't' => s.push('\t'),
+                        'b' => s.push('\u{8}'),
+                        'f' => s.push('\u{c}'),
+                        'u' => {
+                            let hex: String = self.chars.iter().skip(self.i).take(4).collect();
+                            let code = u32::from_str_radix(&hex, 16).map_err(|_| format!("bad escape `\\u{}`", hex))?;
+                            self.i += 4;
+                            s.push(::std::char::from_u32(code).unwrap_or('\u{fffd}'));
+                        },
+                        e => s.push(e)
+                    }
+                },
+                c => s.push(c)
+            }
+        }
+    }
+
+    fn number(&mut self) -> Result<Value, String> {
+        let start = self.i;
+        while let Some(c) = self.peek() {
+            if c.is_ascii_digit() || c == '-' || c == '+' || c == '.' || c == 'e' || c == 'E' {
+                self.i += 1;
+            } else {
+                break;
+            }
+        }
+        let text: String = self.chars[start..self.i].iter().cloned().collect();
+        text.parse().map(Value::Number).map_err(|_| format!("bad number `{}`", text))
+    }
+
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn strings_round_trip() {
+        for s in ["plain", "quote \" and \\ backslash", "lines\nand\ttabs\r", "bell \u{7} and \u{1f}", "ünïcödé ♠"].iter() {
+            assert!(parse(&string(s)) == Ok(Value::String(s.to_string())), "{}", s);
+        }
+    }
+
+    #[test]
+    fn numbers_round_trip() {
+        for &n in [0.0, -1.5, 3.0e-12, 1234567.891, f64::MAX].iter() {
+            assert_eq!(parse(&number(n)).ok().and_then(|v| v.as_f64()), Some(n));
+        }
+        assert_eq!(number(f64::NAN), "null");
+        assert_eq!(number(f64::INFINITY), "null");
+        assert!(parse("-2.5e3") == Ok(Value::Number(-2500.0)));
+    }
+
+    #[test]
+    fn documents_parse_in_order() {
+        let document = "{\n  \"a\": [1, 2, {\"b\": null}],\n  \"c\": {\"d\": true, \"e\": \"f\"},\n  \"g\": []\n}";
+        let value = parse(document).unwrap();
+        assert!(value.path(&["c", "e"]) == Some(&Value::String("f".to_string())));
+        assert!(value.path(&["c", "d"]) == Some(&Value::Bool(true)));
+        match value.get("a") {
+            Some(Value::Array(a)) => assert_eq!(a.len(), 3),
+            _ => panic!("not an array")
+        }
+        assert!(value.path(&["a", "b"]).is_none());
+        match value {
+            Value::Object(ref members) => {
+                let keys: Vec<&str> = members.iter().map(|m| m.0.as_str()).collect();
+                assert_eq!(keys, vec!["a", "c", "g"]);
+            },
+            _ => panic!("not an object")
+        }
+        // compacting keeps the document the same
+        assert!(parse(&compact(document)) == Ok(value.clone()));
+        assert!(!compact(document).contains('\n'));
+    }
+
+    #[test]
+    fn malformed_documents_are_errors() {
+        for text in ["", "{", "[1, 2", "{\"a\" 1}", "\"open", "tru", "1 2", "{\"a\": 1,}", "\"\\uzzzz\""].iter() {
+            assert!(parse(text).is_err(), "{}", text);
+        }
+    }
+
+    #[test]
+    fn optional_values_are_null_when_absent() {
+        assert_eq!(optional(Some(3)), "3");
+        assert_eq!(optional::<u32>(None), "null");
+    }
+}
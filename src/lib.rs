@@ -0,0 +1,27 @@
+//! WarGame Rust
+//!
+//! The game engine (`wg`) and the benchmark harness (`benchmark`)
+//! are exposed here so they can be embedded in other tools.
+//! The `wargame-rust` binary is a thin wrapper around this library.
+
+#[macro_use]
+extern crate log;
+extern crate time;
+extern crate rand;
+extern crate libc;
+
+pub mod wg;
+pub mod benchmark;
+pub mod report;
+pub mod stats;
+pub mod fixed;
+pub mod sweep;
+pub mod affinity;
+pub mod host;
+pub mod history;
+pub mod significance;
+pub mod interval;
+
+mod clock;
+mod json;
+mod workers;
@@ -1,52 +1,200 @@
-#[macro_use]
-extern crate log;
 extern crate env_logger;
-extern crate time;
-extern crate rand;
+extern crate wargame_rust;
 
-pub mod wg;
-pub mod benchmark;
+mod cli;
 
 use std::env;
+use std::fs::File;
+use std::process;
+
+use wargame_rust::benchmark;
+use wargame_rust::fixed;
+use wargame_rust::history;
+use wargame_rust::stats;
+use wargame_rust::sweep;
+
+use cli::{Command, CompareOptions, Format, Options, StatsOptions};
 
 fn main() {
 	env_logger::init().unwrap();
 
-	/*
-		Grab the optional cli argument.
-	*/
-	let args: Vec<String> = env::args().collect();
-
-	let threads:usize;
-	let multiplier:f64;
-
-	let _ = match args.len() {
-		3 => {
-			threads = match args[1].trim().parse() {
-				Ok(x) => x,
-				Err(_) => 1
-			};
-			multiplier = match args[2].trim().parse() {
-				Ok(x) => x,
-				Err(_) => 1.00
-			};
+	let args: Vec<String> = env::args().skip(1).collect();
+
+	let options = match cli::parse(&args) {
+		Ok(Command::Benchmark(options)) => options,
+		Ok(Command::Stats(options)) => {
+			stats(options);
+			return;
+		},
+		Ok(Command::Sweep(options, threads)) => {
+			sweep(options, threads);
+			return;
+		},
+		Ok(Command::Compare(options)) => {
+			compare(options);
+			return;
 		},
-		2 => {
-			threads = match args[1].trim().parse() {
-				Ok(x) => x,
-				Err(_) => 1
-			};
-			multiplier = 1.00;
+		Ok(Command::Help) => {
+			println!("{}", cli::usage());
+			return;
 		},
-		_ => {
-			threads = 1;
-			multiplier = 1.00;
+		Ok(Command::Version) => {
+			println!("wargame-rust {}", env!("CARGO_PKG_VERSION"));
+			return;
+		},
+		Err(e) => {
+			eprintln!("error: {}\n\n{}", e, cli::usage());
+			process::exit(2);
 		}
 	};
 
-	println!("WarGame Rust");
+	if let Some(games) = options.games {
+		fixed(games, options);
+		return;
+	}
+
+	if options.format == Format::Text {
+		println!("WarGame Rust");
+
+		print!("settings: threads = {}; multiplier = {:.2}; prime = {:.1}s; sample = {:.1}s",
+			options.config.threads,
+			options.config.multiplier,
+			options.config.prime_time as f64 / 1e9,
+			options.config.sample_time as f64 / 1e9
+		);
+		if let Some(ref steady) = options.config.steady_state {
+			print!("; steady = {:.2}% over {:.1}s", steady.cov * 100.0, steady.window as f64 / 1e9);
+		}
+		if let Some(precision) = options.config.precision {
+			print!("; precision = {:.2}%", precision * 100.0);
+		}
+		if let Some(seed) = options.config.seed {
+			print!("; seed = {}", seed);
+		}
+		if let Some(ref affinity) = options.config.affinity {
+			print!("; affinity = {}", affinity);
+		}
+		println!("\n");
+	}
+
+	// opened before the run, so a bad path fails before the time is spent
+	let csv = options.csv.as_ref().map(|path| File::create(path).unwrap_or_else(|e| {
+		eprintln!("error: could not create {}: {}", path, e);
+		process::exit(1);
+	}));
+	let history = options.history.as_ref().map(|path| history::open(path).unwrap_or_else(|e| {
+		eprintln!("error: could not open {}: {}", path, e);
+		process::exit(1);
+	}));
+
+	let report = benchmark::benchmark(&options.config);
+
+	// ends the progress line
+	eprintln!();
 
-	println!("settings: threads = {}; multiplier = {:.2}\n", threads, multiplier);
+	match options.format {
+		Format::Json if options.save_samples => println!("{}", report.to_json_with_samples()),
+		Format::Json => println!("{}", report.to_json()),
+		Format::Text => {
+			println!("---\n");
+			println!("{}", report);
+		}
+	}
+
+	// the results are shown first, so a failed write does not lose them
+	let mut failed = false;
 
-	benchmark::benchmark(threads, multiplier.abs());
+	if let (Some(path), Some(mut file)) = (options.csv, csv) {
+		if let Err(e) = report.write_csv(&mut file) {
+			eprintln!("error: could not write {}: {}", path, e);
+			failed = true;
+		}
+	}
+
+	if let (Some(path), Some(mut file)) = (options.history, history) {
+		if let Err(e) = history::append(&mut file, &report, options.save_samples) {
+			eprintln!("error: could not write {}: {}", path, e);
+			failed = true;
+		}
+	}
+
+	if failed {
+		process::exit(1);
+	}
+}
+
+/// Compares two saved runs; a failing run exits with status 1.
+fn compare(options: CompareOptions) {
+	let load = |spec: &str| {
+		history::load(spec).unwrap_or_else(|e| {
+			eprintln!("error: {}", e);
+			process::exit(2);
+		})
+	};
+	let comparison = history::Comparison::new(load(&options.baseline), load(&options.current), options.threshold, options.alpha)
+		.unwrap_or_else(|e| {
+			eprintln!("error: {}", e);
+			process::exit(2);
+		});
+
+	match options.format {
+		Format::Json => println!("{}", comparison.to_json()),
+		Format::Text => println!("{}", comparison)
+	}
+
+	if !comparison.passed() {
+		process::exit(1);
+	}
+}
+
+/// Plays a set number of games and shows how long they took.
+fn fixed(games: u64, options: Options) {
+	if options.format == Format::Text {
+		println!("WarGame Rust");
+		println!("settings: threads = {}; games = {}\n", options.config.threads, games);
+	}
+
+	let report = fixed::run(games, options.config.threads, options.config.seed, &options.config.rules);
+
+	match options.format {
+		Format::Json => println!("{}", report.to_json()),
+		Format::Text => println!("{}", report)
+	}
+}
+
+/// Runs the benchmark for each thread count and shows how the speed scales.
+fn sweep(options: Options, threads: Vec<usize>) {
+	if options.format == Format::Text {
+		println!("WarGame Rust");
+		println!("sweep: threads = {:?}; prime = {:.1}s; sample = {:.1}s\n",
+			threads,
+			options.config.prime_time as f64 / 1e9,
+			options.config.sample_time as f64 / 1e9
+		);
+	}
+
+	let report = sweep::sweep(&options.config, &threads);
+
+	match options.format {
+		Format::Json => println!("{}", report.to_json()),
+		Format::Text => {
+			println!("---\n");
+			println!("{}", report);
+		}
+	}
+}
+
+/// Plays many games and shows how they went.
+fn stats(options: StatsOptions) {
+	let mut results = stats::play(options.games, options.threads, options.seed, &options.rules);
+	results.bins = options.bins;
+
+	match options.format {
+		Format::Json => println!("{}", results.to_json()),
+		Format::Text => {
+			println!("WarGame Rust");
+			println!("stats: games = {}; threads = {}\n", options.games, options.threads);
+			print!("{}", results);
+		}
+	}
 }
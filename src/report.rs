@@ -0,0 +1,394 @@
+use std::fmt;
+use std::io::{self, Write};
+
+use std::collections::HashMap;
+
+use benchmark::{toms, BenchmarkConfig, Measure};
+
+use host::HostInfo;
+
+use json;
+
+const NS:u64 = 1000000000;
+
+/// The version of the JSON document written by `BenchmarkReport::to_json`.
+///
+/// This is bumped whenever a field is renamed, removed or changes meaning;
+/// new fields may be added without a bump.
+pub const JSON_SCHEMA_VERSION: u32 = 3;
+
+/// Gives the descriptive name of a numbered rank criterion.
+pub fn criterion_name(criterion: &str) -> &'static str {
+    match criterion {
+        "1" => "mean_median_within_stdev",
+        "2" => "min_max_within_ten_percent",
+        "3" => "cov_below_one_percent",
+        "4" => "speed_within_one_sigma",
+        "5" => "speed_within_ci",
+        _ => "unknown"
+    }
+}
+
+/// A single point of the speed time series.
+#[derive(Clone, Copy)]
+pub struct SeriesSample {
+    /// Nanoseconds since the start of the run.
+    pub time: u64,
+    /// Games completed since the start of the run.
+    pub games: u64,
+    /// Speed over the sample window that ended with this sample.
+    pub interval_speed: f64,
+    /// Speed since the start of the run.
+    pub speed: f64,
+    /// The phase the sample was taken in: 1 is priming, 2 is sampling.
+    pub phase: u64,
+}
+
+/// The results of a single benchmark run.
+///
+/// Every speed here is in games per nanosecond, exactly as it was sampled;
+/// use `benchmark::toms` to convert to the games per millisecond shown in the output.
+#[derive(Clone)]
+pub struct BenchmarkReport {
+    /// The settings the run was made with.
+    pub config: BenchmarkConfig,
+
+    /// The raw speed samples taken during the sampling phase,
+    /// of the speed `config.measure` names.
+    pub samples: Vec<f64>,
+
+    /// Every sample taken during the run, priming included.
+    pub series: Vec<SeriesSample>,
+
+    pub mean: f64,
+    pub median: f64,
+    pub stdev: f64,
+    pub cov: f64,
+
+    pub minimum_speed: f64,
+    pub maximum_speed: f64,
+    pub min_max_delta: f64,
+
+    pub one_sigma_lower: f64,
+    pub one_sigma_upper: f64,
+    pub one_sigma_delta: f64,
+
+    pub mm_lower: f64,
+    pub mm_upper: f64,
+    pub mean_median_delta: f64,
+
+    /// Games each worker completed during the sampling phase.
+    pub thread_games: Vec<u64>,
+    /// Each worker's speed over the sampling phase.
+    pub thread_speeds: Vec<f64>,
+    /// The slowest worker's speed over the fastest's.
+    pub thread_min_max_ratio: f64,
+    /// Jain's fairness index of the worker speeds; 1 is perfectly fair.
+    pub thread_jain_index: f64,
+
+    /// The CPU each worker was pinned to; `None` where it was not pinned.
+    pub placement: Vec<Option<usize>>,
+
+    /// The confidence interval of the mean, at `config.confidence`, found by `config.interval`.
+    pub ci_lower: f64,
+    pub ci_upper: f64,
+    pub ci_delta: f64,
+    /// How many independent samples the samples are worth.
+    pub effective_samples: f64,
+    /// The confidence interval's half-width, relative to its middle.
+    pub precision: f64,
+    /// With adaptive sampling, whether the precision was reached before the sampling cap.
+    pub precision_reached: Option<bool>,
+
+    /// The final speed, over the entire run.
+    pub speed: f64,
+    /// The speed over the sampling phase alone.
+    pub sample_speed: f64,
+    /// How long priming lasted, in nanoseconds.
+    pub warmup_time: u64,
+    /// With adaptive priming, whether the speed was found steady before the priming cap.
+    pub steady_state: Option<bool>,
+    pub total_games: u64,
+    /// The duration of the run in nanoseconds.
+    pub elapsed_time: u64,
+
+    /// Rank criteria by number, and whether each passed.
+    pub criteria: HashMap<&'static str, bool>,
+    pub rank_passes: usize,
+    pub rank_letter: String,
+    pub rank_reason: String,
+
+    pub score: f64,
+
+    /// Where the run was made, and how the binary was built.
+    pub host: HostInfo,
+}
+
+/// Displays the report as the classic text results block.
+impl fmt::Display for BenchmarkReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Samples: {:9}", self.samples.len())?;
+        writeln!(f, "E.S.S.:\t {:9.1}", self.effective_samples)?;
+        writeln!(f, "Mean:\t {:9.5}", toms(self.mean))?;
+        writeln!(f, "Median:\t {:9.5}", toms(self.median))?;
+        writeln!(f, "S.D.:\t {:9.5}", toms(self.stdev))?;
+        writeln!(f, "C.O.V.:\t {:9.5}", self.cov)?;
+
+        writeln!(f, "---")?;
+
+        writeln!(f, "Min-Max:\t < {:9.5} - {:9.5} > Δ {:9.5}",
+            toms(self.minimum_speed),
+            toms(self.maximum_speed),
+            toms(self.min_max_delta)
+        )?;
+
+        writeln!(f, "1-σ:\t\t < {:9.5} - {:9.5} > Δ {:9.5}",
+            toms(self.one_sigma_lower),
+            toms(self.one_sigma_upper),
+            toms(self.one_sigma_delta)
+        )?;
+
+        writeln!(f, "μ-Median:\t < {:9.5} - {:9.5} > Δ {:9.5}",
+            toms(self.mm_lower),
+            toms(self.mm_upper),
+            toms(self.mean_median_delta)
+        )?;
+
+        writeln!(f, "{}% CI:\t < {:9.5} - {:9.5} > Δ {:9.5} ({})",
+            percent(self.config.confidence),
+            toms(self.ci_lower),
+            toms(self.ci_upper),
+            toms(self.ci_delta),
+            self.config.interval
+        )?;
+
+        write!(f, "Precision:\t ±{:.3}%", self.precision * 100.0)?;
+        match (self.config.precision, self.precision_reached) {
+            (Some(target), Some(true)) => writeln!(f, " (target ±{:.3}%; reached)", target * 100.0)?,
+            (Some(target), _) => writeln!(f, " (target ±{:.3}%; not reached by the cap)", target * 100.0)?,
+            _ => writeln!(f)?
+        }
+
+        let slowest = self.thread_speeds.iter().fold(f64::INFINITY, |a, &b| a.min(b));
+        let fastest = self.thread_speeds.iter().fold(0f64, |a, &b| a.max(b));
+        writeln!(f, "Per-Thread:\t < {:9.5} - {:9.5} > Δ {:9.5}",
+            toms(slowest),
+            toms(fastest),
+            toms(fastest - slowest)
+        )?;
+
+        writeln!(f, "Fairness:\t min/max = {:.5}; Jain = {:.5}",
+            self.thread_min_max_ratio,
+            self.thread_jain_index
+        )?;
+
+        writeln!(f, "---")?;
+
+        writeln!(f, "Threads: {}", self.config.threads)?;
+        if let Some(ref affinity) = self.config.affinity {
+            let placement: Vec<String> = self.placement.iter().map(|p| {
+                p.map_or("-".to_string(), |c| c.to_string())
+            }).collect();
+            writeln!(f, "Affinity: {}", affinity)?;
+            writeln!(f, "Placement: {}", placement.join(" | "))?;
+        }
+        writeln!(f, "Rules: {}", self.config.rules)?;
+        writeln!(f, "Multiplier: {:.2}", self.config.multiplier)?;
+        writeln!(f, "Measure: {}", self.config.measure)?;
+        writeln!(f, "Speed: {:.5} g/ms", toms(self.speed))?;
+        writeln!(f, "Sample Speed: {:.5} g/ms", toms(self.sample_speed))?;
+        writeln!(f, "Games: {}", self.total_games)?;
+        let per_thread: Vec<String> = self.thread_speeds.iter().map(|&s| format!("{:.5}", toms(s))).collect();
+        writeln!(f, "Thread Speeds: {}", per_thread.join(" | "))?;
+        match self.steady_state {
+            Some(true) => writeln!(f, "Warm-Up: {:.1}s (steady)", seconds(self.warmup_time))?,
+            Some(false) => writeln!(f, "Warm-Up: {:.1}s (not steady by the cap)", seconds(self.warmup_time))?,
+            None => writeln!(f, "Warm-Up: {:.1}s (fixed)", seconds(self.warmup_time))?
+        }
+        writeln!(f, "Duration: {:.1}s", seconds(self.elapsed_time))?;
+
+        writeln!(f, "---")?;
+
+        writeln!(f, "{}", self.host)?;
+
+        writeln!(f, "---")?;
+
+        writeln!(f, "Rank: ({}/{}) {}", self.rank_passes, self.criteria.len(), self.rank_letter)?;
+        writeln!(f, "Rank Criteria: {}", self.rank_reason)?;
+
+        writeln!(f, "---")?;
+
+        write!(f, "Score: {}", self.score)
+    }
+}
+
+impl BenchmarkReport {
+
+    /// The speed of the measure the samples were taken of:
+    /// the sampling phase speed for interval samples, the speed over the entire run for cumulative ones.
+    pub fn measured_speed(&self) -> f64 {
+        match self.config.measure {
+            Measure::Interval => self.sample_speed,
+            Measure::Cumulative => self.speed,
+        }
+    }
+
+    /// Renders the report as a single JSON document.
+    ///
+    /// The layout is described in the README under *JSON Output*,
+    /// and is versioned by `JSON_SCHEMA_VERSION`.
+    /// Speeds are in games per millisecond and durations in seconds.
+    pub fn to_json(&self) -> String {
+        self.document(false)
+    }
+
+    /// The same, with the speed samples taken while sampling, as `speed_samples`.
+    pub fn to_json_with_samples(&self) -> String {
+        self.document(true)
+    }
+
+    fn document(&self, samples: bool) -> String {
+        let mut keys: Vec<&&str> = self.criteria.keys().collect();
+        keys.sort();
+        let criteria: Vec<String> = keys.iter().map(|k| {
+            format!("\"{}\": {}", criterion_name(k), self.criteria[**k])
+        }).collect();
+
+        let mut out = String::new();
+        out.push_str("{\n");
+        out.push_str(&format!("  \"schema_version\": {},\n", JSON_SCHEMA_VERSION));
+        out.push_str("  \"settings\": {\n");
+        out.push_str(&format!("    \"threads\": {},\n", self.config.threads));
+        out.push_str(&format!("    \"multiplier\": {},\n", json::number(self.config.multiplier)));
+        out.push_str(&format!("    \"prime_time\": {},\n", json::number(seconds(self.config.prime_time))));
+        out.push_str(&format!("    \"sample_time\": {},\n", json::number(seconds(self.config.sample_time))));
+        out.push_str(&format!("    \"steady_state\": {},\n", json::optional(self.config.steady_state.map(|s| {
+            format!("{{\"window\": {}, \"cov\": {}}}", json::number(seconds(s.window)), json::number(s.cov))
+        }))));
+        out.push_str(&format!("    \"precision\": {},\n", json::optional(self.config.precision.map(json::number))));
+        out.push_str(&format!("    \"sample_frequency\": {},\n", json::number(per_second(self.config.sample_frequency))));
+        out.push_str(&format!("    \"display_frequency\": {},\n", json::number(per_second(self.config.display_frequency))));
+        out.push_str(&format!("    \"seed\": {},\n", json::optional(self.config.seed)));
+        out.push_str(&format!("    \"rules\": {},\n", self.config.rules.to_json()));
+        out.push_str(&format!("    \"measure\": {},\n", json::string(&self.config.measure.to_string())));
+        out.push_str(&format!("    \"confidence\": {},\n", json::number(self.config.confidence)));
+        out.push_str(&format!("    \"interval\": {},\n", json::string(&self.config.interval.to_string())));
+        out.push_str(&format!("    \"affinity\": {},\n", json::optional(self.config.affinity.as_ref().map(|a| json::string(&a.to_string())))));
+        out.push_str(&format!("    \"placement\": [{}]\n", self.placement.iter().map(|&p| json::optional(p)).collect::<Vec<String>>().join(", ")));
+        out.push_str("  },\n");
+        out.push_str(&self.host.to_json_members());
+        out.push_str(",\n");
+        out.push_str("  \"statistics\": {\n");
+        out.push_str(&format!("    \"samples\": {},\n", self.samples.len()));
+        out.push_str(&format!("    \"mean\": {},\n", json::number(toms(self.mean))));
+        out.push_str(&format!("    \"median\": {},\n", json::number(toms(self.median))));
+        out.push_str(&format!("    \"stdev\": {},\n", json::number(toms(self.stdev))));
+        out.push_str(&format!("    \"cov\": {},\n", json::number(self.cov)));
+        out.push_str(&format!("    \"min_max\": {},\n", json_range(self.minimum_speed, self.maximum_speed, self.min_max_delta)));
+        out.push_str(&format!("    \"one_sigma\": {},\n", json_range(self.one_sigma_lower, self.one_sigma_upper, self.one_sigma_delta)));
+        out.push_str(&format!("    \"mean_median\": {},\n", json_range(self.mm_lower, self.mm_upper, self.mean_median_delta)));
+        out.push_str(&format!("    \"effective_samples\": {},\n", json::number(self.effective_samples)));
+        out.push_str(&format!("    \"ci\": {},\n", json_range(self.ci_lower, self.ci_upper, self.ci_delta)));
+        out.push_str(&format!("    \"precision\": {},\n", json::number(self.precision)));
+        out.push_str(&format!("    \"per_thread\": {{\"games\": [{}], \"speeds\": [{}], \"min_max_ratio\": {}, \"jain_index\": {}}}\n",
+            self.thread_games.iter().map(|g| g.to_string()).collect::<Vec<String>>().join(", "),
+            self.thread_speeds.iter().map(|&s| json::number(toms(s))).collect::<Vec<String>>().join(", "),
+            json::number(self.thread_min_max_ratio),
+            json::number(self.thread_jain_index)
+        ));
+        out.push_str("  },\n");
+        out.push_str("  \"rank\": {\n");
+        out.push_str(&format!("    \"letter\": {},\n", json::string(&self.rank_letter)));
+        out.push_str(&format!("    \"passes\": {},\n", self.rank_passes));
+        out.push_str(&format!("    \"total\": {},\n", self.criteria.len()));
+        out.push_str(&format!("    \"criteria\": {{{}}}\n", criteria.join(", ")));
+        out.push_str("  },\n");
+        out.push_str(&format!("  \"speed\": {},\n", json::number(toms(self.speed))));
+        out.push_str(&format!("  \"sample_speed\": {},\n", json::number(toms(self.sample_speed))));
+        out.push_str(&format!("  \"games\": {},\n", self.total_games));
+        out.push_str(&format!("  \"warmup_time\": {},\n", json::number(seconds(self.warmup_time))));
+        out.push_str(&format!("  \"steady_state\": {},\n", json::optional(self.steady_state)));
+        out.push_str(&format!("  \"precision_reached\": {},\n", json::optional(self.precision_reached)));
+        out.push_str(&format!("  \"duration\": {},\n", json::number(seconds(self.elapsed_time))));
+        out.push_str(&format!("  \"score\": {}", json::number(self.score)));
+        if samples {
+            out.push_str(&format!(",\n  \"speed_samples\": [{}]",
+                self.samples.iter().map(|&s| json::number(toms(s))).collect::<Vec<String>>().join(", ")));
+        }
+        out.push_str("\n}");
+        out
+    }
+
+}
+
+impl BenchmarkReport {
+
+    /// Writes the speed time series as CSV, one row per sample.
+    ///
+    /// Times are in seconds since the start of the run,
+    /// and speeds in games per millisecond.
+    pub fn write_csv<W: Write>(&self, w: &mut W) -> io::Result<()> {
+        writeln!(w, "time,games,interval_speed,speed,phase")?;
+        for s in self.series.iter() {
+            writeln!(w, "{:.6},{},{:.5},{:.5},{}",
+                seconds(s.time),
+                s.games,
+                toms(s.interval_speed),
+                toms(s.speed),
+                s.phase
+            )?;
+        }
+        Ok(())
+    }
+
+}
+
+fn seconds(ns: u64) -> f64 {
+    ns as f64 / NS as f64
+}
+
+/// Turns the time between two events into events per second.
+fn per_second(ns: u64) -> f64 {
+    NS as f64 / ns as f64
+}
+
+/// A fraction as a percentage, such as `99.9` for 0.999.
+pub(crate) fn percent(fraction: f64) -> String {
+    let p = format!("{:.4}", fraction * 100.0);
+    p.trim_end_matches('0').trim_end_matches('.').to_string()
+}
+
+/// A speed interval, in games per millisecond.
+fn json_range(lower: f64, upper: f64, delta: f64) -> String {
+    format!("{{\"lower\": {}, \"upper\": {}, \"delta\": {}}}",
+        json::number(toms(lower)),
+        json::number(toms(upper)),
+        json::number(toms(delta))
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use benchmark;
+
+
+    #[test]
+    fn csv_has_a_row_per_sample() {
+        let report: BenchmarkReport = benchmark::benchmark(&benchmark::BenchmarkConfig::new(1, 0.01));
+        let mut out = Vec::new();
+        report.write_csv(&mut out).unwrap();
+        let text = String::from_utf8(out).unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines[0], "time,games,interval_speed,speed,phase");
+        assert_eq!(lines.len(), report.series.len() + 1);
+        for (line, sample) in lines[1..].iter().zip(report.series.iter()) {
+            let fields: Vec<&str> = line.split(',').collect();
+            assert_eq!(fields.len(), 5);
+            assert_eq!(fields[1], sample.games.to_string());
+            assert_eq!(fields[4], sample.phase.to_string());
+        }
+        assert!(report.series.iter().any(|s| s.phase == 1));
+        assert!(report.series.iter().any(|s| s.phase == 2));
+    }
+}
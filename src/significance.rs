@@ -0,0 +1,296 @@
+//! Tests of whether two sets of speed samples differ.
+//!
+//! Welch's t-test compares the means without assuming equal variances.
+//! The Mann-Whitney U test compares the samples by rank, so a few
+//! outlying samples cannot sway it. Both are two-sided, and both take
+//! the samples to be independent; see `interval::batch_means`.
+
+use benchmark::{get_mean, get_standard_deviation};
+
+/// The result of Welch's t-test of `b` against `a`.
+#[derive(Clone, Copy)]
+pub struct WelchTest {
+    /// Positive when `b` has the higher mean.
+    pub t: f64,
+    /// The Welch-Satterthwaite degrees of freedom.
+    pub degrees_of_freedom: f64,
+    pub p_value: f64,
+    /// The difference in means over the pooled standard deviation.
+    pub cohens_d: f64,
+}
+
+/// The result of the Mann-Whitney U test of `b` against `a`.
+#[derive(Clone, Copy)]
+pub struct MannWhitneyTest {
+    /// How many of the pairs of one sample from each set have `b` faster, ties counting half.
+    pub u: f64,
+    /// `u` normalized, with the correction for ties.
+    pub z: f64,
+    pub p_value: f64,
+    /// From -1, every `b` slower than every `a`, to 1, every `b` faster.
+    pub rank_biserial: f64,
+}
+
+/// Welch's t-test; there is none without two samples in each set, and some spread.
+pub fn welch(a: &[f64], b: &[f64]) -> Option<WelchTest> {
+    if a.len() < 2 || b.len() < 2 {
+        return None;
+    }
+    let (na, nb) = (a.len() as f64, b.len() as f64);
+    let (mean_a, mean_b) = (get_mean(a), get_mean(b));
+    let (sd_a, sd_b) = (get_standard_deviation(a, mean_a), get_standard_deviation(b, mean_b));
+
+    // the squared standard errors
+    let (va, vb) = (sd_a * sd_a / na, sd_b * sd_b / nb);
+    if va + vb == 0.0 {
+        return None;
+    }
+
+    let t = (mean_b - mean_a) / (va + vb).sqrt();
+    let df = (va + vb).powi(2) / (va * va / (na - 1.0) + vb * vb / (nb - 1.0));
+    let pooled = (((na - 1.0) * sd_a * sd_a + (nb - 1.0) * sd_b * sd_b) / (na + nb - 2.0)).sqrt();
+
+    Some(WelchTest {
+        t,
+        degrees_of_freedom: df,
+        p_value: t_p_value(t, df),
+        cohens_d: (mean_b - mean_a) / pooled,
+    })
+}
+
+/// The Mann-Whitney U test, by the normal approximation, which suits the thousands
+/// of samples a run takes; there is none when either set is empty, or every sample is the same.
+pub fn mann_whitney(a: &[f64], b: &[f64]) -> Option<MannWhitneyTest> {
+    if a.is_empty() || b.is_empty() {
+        return None;
+    }
+    let (na, nb) = (a.len() as f64, b.len() as f64);
+    let n = na + nb;
+
+    let mut all: Vec<(f64, bool)> = a.iter().map(|&s| (s, false)).chain(b.iter().map(|&s| (s, true))).collect();
+    all.sort_by(|x, y| x.0.partial_cmp(&y.0).unwrap());
+
+    // ties share the mean of their ranks
+    let mut rank_sum_b = 0f64;
+    let mut ties = 0f64;
+    let mut i = 0;
+    while i < all.len() {
+        let mut j = i;
+        while j + 1 < all.len() && all[j + 1].0 == all[i].0 {
+            j += 1;
+        }
+        let count = (j - i + 1) as f64;
+        let rank = (i + j) as f64 / 2.0 + 1.0;
+        rank_sum_b += rank * all[i..j + 1].iter().filter(|s| s.1).count() as f64;
+        ties += count * count * count - count;
+        i = j + 1;
+    }
+
+    let u = rank_sum_b - nb * (nb + 1.0) / 2.0;
+    let mean_u = na * nb / 2.0;
+    let sd_u = (na * nb / 12.0 * ((n + 1.0) - ties / (n * (n - 1.0)))).sqrt();
+    if sd_u.is_nan() || sd_u <= 0.0 {
+        return None;
+    }
+
+    // with the continuity correction
+    let delta = u - mean_u;
+    let z = (delta - 0.5 * delta.signum()) / sd_u;
+
+    Some(MannWhitneyTest {
+        u,
+        z,
+        p_value: erfc(z.abs() / 2f64.sqrt()).min(1.0),
+        rank_biserial: 2.0 * u / (na * nb) - 1.0,
+    })
+}
+
+/// The two-sided p-value of Student's t with `df` degrees of freedom.
+fn t_p_value(t: f64, df: f64) -> f64 {
+    incomplete_beta(df / 2.0, 0.5, df / (df + t * t))
+}
+
+/// The `t` such that Student's t with `df` degrees of freedom falls within `±t`
+/// with probability `confidence`; such as 3.291 for 99.9% with many degrees of freedom.
+pub(crate) fn t_critical(confidence: f64, df: f64) -> f64 {
+    let alpha = 1.0 - confidence;
+
+    let mut low = 0f64;
+    let mut high = 1f64;
+    while t_p_value(high, df) > alpha && high < 1e12 {
+        high *= 2.0;
+    }
+    // the p-value falls as t grows
+    for _ in 0..200 {
+        let middle = (low + high) / 2.0;
+        if t_p_value(middle, df) > alpha {
+            low = middle;
+        } else {
+            high = middle;
+        }
+    }
+    (low + high) / 2.0
+}
+
+/// The regularized incomplete beta function `I_x(a, b)`,
+/// by its continued fraction (Numerical Recipes, 6.4).
+fn incomplete_beta(a: f64, b: f64, x: f64) -> f64 {
+    if x <= 0.0 {
+        return 0.0;
+    }
+    if x >= 1.0 {
+        return 1.0;
+    }
+    let front = (ln_gamma(a + b) - ln_gamma(a) - ln_gamma(b) + a * x.ln() + b * (1.0 - x).ln()).exp();
+    // the fraction converges quickly only on this side
+    if x < (a + 1.0) / (a + b + 2.0) {
+        front * beta_fraction(a, b, x) / a
+    } else {
+        1.0 - front * beta_fraction(b, a, 1.0 - x) / b
+    }
+}
+
+fn beta_fraction(a: f64, b: f64, x: f64) -> f64 {
+    const TINY:f64 = 1e-300;
+    const EPSILON:f64 = 1e-15;
+
+    let mut c = 1f64;
+    let mut d = 1.0 - (a + b) * x / (a + 1.0);
+    if d.abs() < TINY {
+        d = TINY;
+    }
+    d = 1.0 / d;
+    let mut h = d;
+
+    for m in 1..1000 {
+        let m = m as f64;
+        for &numerator in [
+            m * (b - m) * x / ((a + 2.0 * m - 1.0) * (a + 2.0 * m)),
+            -(a + m) * (a + b + m) * x / ((a + 2.0 * m) * (a + 2.0 * m + 1.0))
+        ].iter() {
+            d = 1.0 + numerator * d;
+            if d.abs() < TINY {
+                d = TINY;
+            }
+            c = 1.0 + numerator / c;
+            if c.abs() < TINY {
+                c = TINY;
+            }
+            d = 1.0 / d;
+            h *= d * c;
+        }
+        if (d * c - 1.0).abs() < EPSILON {
+            break;
+        }
+    }
+    h
+}
+
+/// The log of the gamma function, by the Lanczos approximation (g = 7).
+fn ln_gamma(x: f64) -> f64 {
+    const COEFFICIENTS: [f64; 9] = [
+        0.9999999999998099, 676.5203681218851, -1259.1392167224028,
+        771.3234287776531, -176.6150291621406, 12.507343278686905,
+        -0.13857109526572012, 9.984369578019572e-6, 1.5056327351493116e-7
+    ];
+    let x = x - 1.0;
+    let mut sum = COEFFICIENTS[0];
+    for (i, &c) in COEFFICIENTS.iter().enumerate().skip(1) {
+        sum += c / (x + i as f64);
+    }
+    let t = x + 7.5;
+    0.5 * (2.0 * ::std::f64::consts::PI).ln() + (x + 0.5) * t.ln() - t + sum.ln()
+}
+
+/// The complementary error function, to a relative error below 1.2e-7
+/// (Numerical Recipes, 6.2).
+fn erfc(x: f64) -> f64 {
+    let z = x.abs();
+    let t = 1.0 / (1.0 + 0.5 * z);
+    let r = t * (-z * z - 1.26551223 + t * (1.00002368 + t * (0.37409196 + t * (0.09678418
+        + t * (-0.18628806 + t * (0.27886807 + t * (-1.13520398 + t * (1.48851587
+        + t * (-0.82215223 + t * 0.17087277))))))))).exp();
+    if x >= 0.0 {
+        r
+    } else {
+        2.0 - r
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn close(a: f64, b: f64, tolerance: f64) -> bool {
+        (a - b).abs() <= tolerance * b.abs().max(1.0)
+    }
+
+    #[test]
+    fn welch_matches_reference() {
+        let t = welch(&[1.0, 2.0, 3.0, 4.0, 5.0], &[2.0, 4.0, 6.0, 8.0, 10.0]).unwrap();
+        assert!(close(t.t, 1.8973665961010275, 1e-12));
+        assert!(close(t.degrees_of_freedom, 5.882352941176471, 1e-12));
+        assert!(close(t.p_value, 0.10753119493062724, 1e-8));
+        assert!(close(t.cohens_d, 1.2, 1e-12));
+
+        assert!(welch(&[1.0], &[1.0, 2.0]).is_none());
+        assert!(welch(&[1.0, 1.0], &[1.0, 1.0]).is_none());
+    }
+
+    #[test]
+    fn mann_whitney_matches_reference() {
+        // with ties
+        let u = mann_whitney(&[1.0, 2.0, 3.0, 4.0, 5.0], &[3.0, 4.0, 5.0, 6.0, 7.0]).unwrap();
+        assert!(close(u.u, 20.5, 1e-12));
+        assert!(close(u.z, 1.5811388300841898, 1e-12));
+        assert!(close(u.p_value, 0.11384629800665808, 1e-6));
+        assert!(close(u.rank_biserial, 0.64, 1e-12));
+
+        // every `b` faster, and then slower
+        let u = mann_whitney(&[1.1, 2.2, 3.3, 4.4], &[5.5, 6.6, 7.7, 8.8]).unwrap();
+        assert!(close(u.p_value, 0.03038282197657751, 1e-6));
+        assert_eq!(u.rank_biserial, 1.0);
+        let u = mann_whitney(&[5.5, 6.6, 7.7, 8.8], &[1.1, 2.2, 3.3, 4.4]).unwrap();
+        assert_eq!(u.rank_biserial, -1.0);
+
+        assert!(mann_whitney(&[], &[1.0]).is_none());
+        assert!(mann_whitney(&[2.0, 2.0], &[2.0]).is_none());
+    }
+
+    #[test]
+    fn erfc_matches_reference() {
+        assert!(close(erfc(1.0), 0.15729920705028513, 1.2e-7));
+        assert!(close(erfc(0.3), 0.6713732405408726, 1.2e-7));
+        assert!(close(erfc(-1.0), 1.842700792949715, 1.2e-7));
+    }
+
+    #[test]
+    fn t_critical_matches_the_tables() {
+        assert!(close(t_critical(0.95, 9.0), 2.262157162798205, 1e-9));
+        assert!(close(t_critical(0.999, 18.0), 3.921645825085159, 1e-9));
+        assert!(close(t_critical(0.95, 1.0), 12.706204736174693, 1e-9));
+        assert!(close(t_critical(0.99, 1e6), 2.575834220105334, 1e-9));
+    }
+
+    #[test]
+    fn t_p_value_matches_reference() {
+        assert!(close(t_p_value(2.0, 10.0), 0.07338803477072076, 1e-9));
+        assert!(close(t_p_value(0.0, 5.0), 1.0, 1e-12));
+    }
+
+    #[test]
+    fn incomplete_beta_matches_reference() {
+        assert!(close(incomplete_beta(2.0, 3.0, 0.4), 0.5248, 1e-10));
+        assert!(close(incomplete_beta(0.5, 5.0, 0.3), 0.9347377538310918, 1e-10));
+        assert!(close(incomplete_beta(4.0, 4.0, 0.5), 0.5, 1e-12));
+        assert_eq!(incomplete_beta(2.0, 3.0, 0.0), 0.0);
+        assert_eq!(incomplete_beta(2.0, 3.0, 1.0), 1.0);
+    }
+
+    #[test]
+    fn ln_gamma_matches_reference() {
+        assert!(close(ln_gamma(0.5), 0.5723649429247001, 1e-12));
+        assert!(close(ln_gamma(10.0), 12.80182748008147, 1e-12));
+        assert!(close(ln_gamma(3.7), 1.428072326665388, 1e-12));
+    }
+}
@@ -0,0 +1,448 @@
+//! Statistics about the WarGame itself, rather than its speed.
+//!
+//! Many games are played and every `GameOutcome` is tallied,
+//! giving the distribution of game length, wars and war chains,
+//! and how often each player wins.
+//! Games of more than two players are tallied from their
+//! `MultiplayerOutcome`, with the place each player finished in.
+
+use std::fmt;
+
+use std::thread;
+
+use rand;
+use rand::Rng;
+
+use wg;
+use wg::{Ending, GameOutcome, MultiplayerOutcome, Rules, Winner};
+
+use json;
+
+/// A distribution of small counts, such as turns per game.
+///
+/// Each value is tallied exactly, so percentiles are exact
+/// and distributions from several threads can be merged.
+#[derive(Clone)]
+pub struct Distribution {
+    counts: Vec<u64>,
+    total: u64,
+    sum: u64,
+}
+
+impl Distribution {
+
+    /// An empty distribution.
+    pub fn new() -> Distribution {
+        Distribution {counts: Vec::new(), total: 0, sum: 0}
+    }
+
+    /// Tallies one value.
+    pub fn add(&mut self, value: u32) {
+        let v = value as usize;
+        if v >= self.counts.len() {
+            self.counts.resize(v + 1, 0);
+        }
+        self.counts[v] += 1;
+        self.total += 1;
+        self.sum += value as u64;
+    }
+
+    /// Adds every value of another distribution to this one.
+    pub fn merge(&mut self, other: &Distribution) {
+        if other.counts.len() > self.counts.len() {
+            self.counts.resize(other.counts.len(), 0);
+        }
+        for (v, &c) in other.counts.iter().enumerate() {
+            self.counts[v] += c;
+        }
+        self.total += other.total;
+        self.sum += other.sum;
+    }
+
+    /// The number of values tallied.
+    pub fn total(&self) -> u64 {
+        self.total
+    }
+
+    pub fn mean(&self) -> f64 {
+        self.sum as f64 / self.total as f64
+    }
+
+    pub fn min(&self) -> u32 {
+        self.counts.iter().position(|&c| c > 0).unwrap_or(0) as u32
+    }
+
+    pub fn max(&self) -> u32 {
+        self.counts.iter().rposition(|&c| c > 0).unwrap_or(0) as u32
+    }
+
+    /// The smallest value at or below which `p` percent of the values fall.
+    pub fn percentile(&self, p: f64) -> u32 {
+        let rank = ((p / 100.0) * self.total as f64).ceil().max(1.0) as u64;
+        let mut seen:u64 = 0;
+        for (v, &c) in self.counts.iter().enumerate() {
+            seen += c;
+            if seen >= rank {
+                return v as u32;
+            }
+        }
+        self.max()
+    }
+
+    /// Groups the values into at most `bins` equally wide ranges.
+    ///
+    /// Each bin is `(lower, upper, count)`, with both bounds inclusive.
+    pub fn histogram(&self, bins: usize) -> Vec<(u32, u32, u64)> {
+        if self.total == 0 || bins == 0 {
+            return Vec::new();
+        }
+        let min = self.min();
+        let max = self.max();
+        let span = (max - min) as usize + 1;
+        let width = span.div_ceil(bins);
+
+        let mut histogram = Vec::new();
+        let mut lower = min as usize;
+        while lower <= max as usize {
+            let upper = (lower + width - 1).min(max as usize);
+            let count = self.counts[lower..upper + 1].iter().sum::<u64>();
+            histogram.push((lower as u32, upper as u32, count));
+            lower = upper + 1;
+        }
+        histogram
+    }
+
+}
+
+/// The same as `Distribution::new`.
+impl Default for Distribution {
+    fn default() -> Distribution {
+        Distribution::new()
+    }
+}
+
+/// Tallies of many games of War.
+#[derive(Clone)]
+pub struct GameStats {
+    pub games: u64,
+    pub player1_wins: u64,
+    pub player2_wins: u64,
+    pub draws: u64,
+    /// Wins of each player, counting from the first; for any number of players.
+    pub wins: Vec<u64>,
+    /// The place each player finished in, 1 for the winner,
+    /// over the games that were not drawn.
+    pub places: Vec<Distribution>,
+    /// Games that ended because a player had too few cards for a war.
+    pub war_exhaustion: u64,
+    /// Games stopped at the turn cap, and because they cycled; both are draws.
+    pub turn_caps: u64,
+    pub cycles: u64,
+    pub turns: Distribution,
+    pub wars: Distribution,
+    /// The deepest war chain of each game.
+    pub deepest_war: Distribution,
+    /// How many bins the histograms are shown with.
+    pub bins: usize,
+    /// The rules the games were played by.
+    pub rules: Rules,
+}
+
+impl GameStats {
+
+    pub fn new() -> GameStats {
+        GameStats {
+            games: 0,
+            player1_wins: 0,
+            player2_wins: 0,
+            draws: 0,
+            wins: Vec::new(),
+            places: Vec::new(),
+            war_exhaustion: 0,
+            turn_caps: 0,
+            cycles: 0,
+            turns: Distribution::new(),
+            wars: Distribution::new(),
+            deepest_war: Distribution::new(),
+            bins: 10,
+            rules: Rules::new(),
+        }
+    }
+
+    /// Tallies one game.
+    pub fn add(&mut self, outcome: &GameOutcome) {
+        self.games += 1;
+        self.seat(2);
+        match outcome.winner {
+            Winner::Player1 => {
+                self.player1_wins += 1;
+                self.place(&[0, 1]);
+            },
+            Winner::Player2 => {
+                self.player2_wins += 1;
+                self.place(&[1, 0]);
+            },
+            Winner::Draw => self.draws += 1,
+        }
+        if outcome.war_exhaustion {
+            self.war_exhaustion += 1;
+        }
+        self.end(outcome.ending);
+        self.turns.add(outcome.turns);
+        self.wars.add(outcome.wars);
+        self.deepest_war.add(outcome.deepest_war);
+    }
+
+    /// Tallies one game of more than two players.
+    pub fn add_multiplayer(&mut self, outcome: &MultiplayerOutcome) {
+        let players = outcome.cards.len();
+        self.games += 1;
+        self.seat(players);
+        match outcome.winner {
+            Some(winner) => {
+                match winner {
+                    0 => self.player1_wins += 1,
+                    1 => self.player2_wins += 1,
+                    _ => {}
+                }
+                // the last out is second, and the first out is last
+                let mut order = outcome.eliminated.clone();
+                order.push(winner);
+                order.reverse();
+                self.place(&order);
+            },
+            None => self.draws += 1,
+        }
+        if outcome.war_exhaustion {
+            self.war_exhaustion += 1;
+        }
+        self.end(outcome.ending);
+        self.turns.add(outcome.turns);
+        self.wars.add(outcome.wars);
+        self.deepest_war.add(outcome.deepest_war);
+    }
+
+    fn end(&mut self, ending: Ending) {
+        match ending {
+            Ending::Finished => {},
+            Ending::TurnCap => self.turn_caps += 1,
+            Ending::Cycle => self.cycles += 1,
+        }
+    }
+
+    /// Makes room for the tallies of `players` players.
+    fn seat(&mut self, players: usize) {
+        if self.wins.len() < players {
+            self.wins.resize(players, 0);
+            self.places.resize(players, Distribution::new());
+        }
+    }
+
+    /// Tallies the finishing order of a game that was won, the winner first.
+    fn place(&mut self, order: &[usize]) {
+        self.wins[order[0]] += 1;
+        for (place, &player) in order.iter().enumerate() {
+            self.places[player].add(place as u32 + 1);
+        }
+    }
+
+    /// Adds every game of another tally to this one.
+    pub fn merge(&mut self, other: &GameStats) {
+        self.games += other.games;
+        self.player1_wins += other.player1_wins;
+        self.player2_wins += other.player2_wins;
+        self.draws += other.draws;
+        self.seat(other.wins.len());
+        for (i, &w) in other.wins.iter().enumerate() {
+            self.wins[i] += w;
+            self.places[i].merge(&other.places[i]);
+        }
+        self.war_exhaustion += other.war_exhaustion;
+        self.turn_caps += other.turn_caps;
+        self.cycles += other.cycles;
+        self.turns.merge(&other.turns);
+        self.wars.merge(&other.wars);
+        self.deepest_war.merge(&other.deepest_war);
+    }
+
+    /// How often the first player wins, out of all games.
+    pub fn player1_win_rate(&self) -> f64 {
+        self.player1_wins as f64 / self.games as f64
+    }
+
+    /// Renders the tallies as a single JSON document.
+    pub fn to_json(&self) -> String {
+        let mut out = String::new();
+        out.push_str("{\n");
+        out.push_str(&format!("  \"rules\": {},\n", self.rules.to_json()));
+        out.push_str(&format!("  \"games\": {},\n", self.games));
+        out.push_str(&format!("  \"player1_wins\": {},\n", self.player1_wins));
+        out.push_str(&format!("  \"player2_wins\": {},\n", self.player2_wins));
+        out.push_str(&format!("  \"draws\": {},\n", self.draws));
+        out.push_str(&format!("  \"player1_win_rate\": {},\n", json::number(self.player1_win_rate())));
+        let players: Vec<String> = self.wins.iter().zip(self.places.iter()).map(|(&w, p)| {
+            format!("{{\"wins\": {}, \"win_rate\": {}, \"mean_place\": {}}}",
+                w, json::number(w as f64 / self.games as f64), json::number(p.mean()))
+        }).collect();
+        out.push_str(&format!("  \"players\": [{}],\n", players.join(", ")));
+        out.push_str(&format!("  \"war_exhaustion\": {},\n", self.war_exhaustion));
+        out.push_str(&format!("  \"turn_caps\": {},\n", self.turn_caps));
+        out.push_str(&format!("  \"cycles\": {},\n", self.cycles));
+        out.push_str(&format!("  \"turns\": {},\n", json_distribution(&self.turns, self.bins)));
+        out.push_str(&format!("  \"wars\": {},\n", json_distribution(&self.wars, self.bins)));
+        out.push_str(&format!("  \"deepest_war\": {}\n", json_distribution(&self.deepest_war, self.bins)));
+        out.push('}');
+        out
+    }
+
+}
+
+/// The same as `GameStats::new`.
+impl Default for GameStats {
+    fn default() -> GameStats {
+        GameStats::new()
+    }
+}
+
+/// Displays the tallies, with a histogram of each distribution.
+impl fmt::Display for GameStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Rules: {}", self.rules)?;
+        writeln!(f, "Games: {}", self.games)?;
+        if self.wins.len() > 2 {
+            for (i, (&w, p)) in self.wins.iter().zip(self.places.iter()).enumerate() {
+                writeln!(f, "P{} Wins: {} ({:.2}%); mean place = {:.2}", i + 1, w, percent(w, self.games), p.mean())?;
+            }
+        } else {
+            writeln!(f, "P1 Wins: {} ({:.2}%)", self.player1_wins, percent(self.player1_wins, self.games))?;
+            writeln!(f, "P2 Wins: {} ({:.2}%)", self.player2_wins, percent(self.player2_wins, self.games))?;
+        }
+        writeln!(f, "Draws: {} ({:.2}%)", self.draws, percent(self.draws, self.games))?;
+        writeln!(f, "War Exhaustion: {} ({:.2}%)", self.war_exhaustion, percent(self.war_exhaustion, self.games))?;
+        if self.rules.max_turns.is_some() {
+            writeln!(f, "Turn Caps: {} ({:.2}%)", self.turn_caps, percent(self.turn_caps, self.games))?;
+        }
+        if self.rules.detects_cycles() {
+            writeln!(f, "Cycles: {} ({:.2}%)", self.cycles, percent(self.cycles, self.games))?;
+        }
+
+        for &(name, d) in [("Turns", &self.turns), ("Wars", &self.wars), ("Deepest War", &self.deepest_war)].iter() {
+            writeln!(f, "---")?;
+            writeln!(f, "{}:\t mean = {:.2}; min = {}; p50 = {}; p90 = {}; p99 = {}; max = {}",
+                name, d.mean(), d.min(), d.percentile(50.0), d.percentile(90.0), d.percentile(99.0), d.max())?;
+
+            let histogram = d.histogram(self.bins);
+            let most = histogram.iter().fold(0, |a, &(_, _, c)| a.max(c));
+            for &(lower, upper, count) in histogram.iter() {
+                let bar = (count * 40).checked_div(most).unwrap_or(0) as usize;
+                writeln!(f, "  {:6} - {:6} | {:8} {}", lower, upper, count, repeat('#', bar))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Plays `games` games across `threads` threads and tallies them.
+///
+/// With a seed, thread `i` plays with `seed + i`, the same as the benchmark workers,
+/// so a given seed and thread count always gives the same tallies.
+pub fn play(games: u64, threads: usize, seed: Option<u64>, rules: &Rules) -> GameStats {
+    let mut handles = Vec::with_capacity(threads);
+
+    for i in 0..threads {
+        // the remainder is spread over the first threads
+        let share = games / threads as u64 + if (i as u64) < games % threads as u64 {1} else {0};
+        let rules = rules.clone();
+
+        handles.push(thread::spawn(move || {
+            match seed {
+                Some(seed) => tally(share, &rules, &mut wg::seeded_rng(seed.wrapping_add(i as u64))),
+                None => tally(share, &rules, &mut rand::thread_rng())
+            }
+        }));
+    }
+
+    let mut stats = GameStats::new();
+    stats.rules = rules.clone();
+    for h in handles {
+        stats.merge(&h.join().unwrap());
+    }
+    stats
+}
+
+fn tally<R: Rng>(games: u64, rules: &Rules, rng: &mut R) -> GameStats {
+    let mut stats = GameStats::new();
+    for _ in 0..games {
+        if rules.players > 2 {
+            stats.add_multiplayer(&wg::multiplayer_game(rng, rules));
+        } else {
+            stats.add(&wg::game_with_rules(rng, rules));
+        }
+    }
+    stats
+}
+
+fn percent(n: u64, total: u64) -> f64 {
+    n as f64 * 100.0 / total as f64
+}
+
+fn repeat(c: char, n: usize) -> String {
+    (0..n).map(|_| c).collect()
+}
+
+fn json_distribution(d: &Distribution, bins: usize) -> String {
+    let histogram: Vec<String> = d.histogram(bins).iter().map(|&(lower, upper, count)| {
+        format!("{{\"lower\": {}, \"upper\": {}, \"count\": {}}}", lower, upper, count)
+    }).collect();
+    format!("{{\"mean\": {}, \"min\": {}, \"p50\": {}, \"p90\": {}, \"p99\": {}, \"max\": {}, \"histogram\": [{}]}}",
+        json::number(d.mean()), d.min(), d.percentile(50.0), d.percentile(90.0), d.percentile(99.0), d.max(),
+        histogram.join(", ")
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn distribution_percentiles_are_exact() {
+        let mut d = Distribution::new();
+        for v in 1..101 {
+            d.add(v);
+        }
+        assert_eq!(d.total(), 100);
+        assert_eq!(d.min(), 1);
+        assert_eq!(d.max(), 100);
+        assert_eq!(d.percentile(50.0), 50);
+        assert_eq!(d.percentile(99.0), 99);
+        assert!((d.mean() - 50.5).abs() < 1e-12);
+        let histogram = d.histogram(10);
+        assert_eq!(histogram.len(), 10);
+        assert_eq!(histogram[0], (1, 10, 10));
+    }
+
+    #[test]
+    fn two_player_wins_match_the_winners() {
+        let stats = play(500, 2, Some(7), &Rules::new());
+        assert_eq!(stats.wins, vec![stats.player1_wins, stats.player2_wins]);
+        assert_eq!(stats.player1_wins + stats.player2_wins + stats.draws, stats.games);
+        // the winner of a game places first, and the loser second
+        assert_eq!(stats.places[0].total(), stats.games - stats.draws);
+        let first = stats.player1_wins as f64 / stats.places[0].total() as f64;
+        assert!((stats.places[0].mean() - (2.0 - first)).abs() < 1e-9);
+    }
+
+    #[test]
+    fn multiplayer_places_add_up() {
+        let mut rules = Rules::new();
+        rules.players = 4;
+        let stats = play(300, 1, Some(3), &rules);
+        let wins: u64 = stats.wins.iter().sum();
+        assert_eq!(wins + stats.draws, stats.games);
+        assert_eq!(stats.wins[0], stats.player1_wins);
+        assert_eq!(stats.wins[1], stats.player2_wins);
+        // every game won places each player once, from 1 to 4
+        let total: f64 = stats.places.iter().map(|p| p.mean()).sum();
+        assert!((total - 10.0).abs() < 1e-9);
+    }
+}
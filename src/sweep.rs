@@ -0,0 +1,202 @@
+//! The thread scaling sweep.
+//!
+//! The benchmark is run once for each thread count, and each speed is
+//! compared against the single thread speed, giving the speedup and
+//! parallel efficiency, and fits of Amdahl's and Gustafson's laws.
+
+use std::fmt;
+
+use benchmark;
+use benchmark::{toms, BenchmarkConfig};
+use report::BenchmarkReport;
+use host::HostInfo;
+
+use json;
+
+/// One benchmark run of a sweep.
+#[derive(Clone)]
+pub struct SweepPoint {
+    pub threads: usize,
+    /// Speed relative to the single thread run.
+    pub speedup: f64,
+    /// Speedup per thread; 1 is perfect scaling.
+    pub efficiency: f64,
+    pub report: BenchmarkReport,
+}
+
+/// The results of a thread scaling sweep.
+#[derive(Clone)]
+pub struct SweepReport {
+    /// One point per thread count, in increasing order, starting at one thread.
+    pub points: Vec<SweepPoint>,
+    /// Amdahl's law: the fraction of the work that runs in parallel,
+    /// fitted by least squares to `1 / speedup = (1 - p) + p / threads`.
+    pub amdahl_parallel_fraction: Option<f64>,
+    /// Gustafson's law: the serial fraction,
+    /// fitted by least squares to `speedup = threads - s * (threads - 1)`.
+    pub gustafson_serial_fraction: Option<f64>,
+    /// Where the sweep was made, with the load averages from its start and end.
+    pub host: HostInfo,
+}
+
+impl SweepReport {
+
+    /// The greatest speedup Amdahl's law allows with the fitted parallel fraction.
+    ///
+    /// There is no limit when the whole of the work fits as parallel.
+    pub fn amdahl_max_speedup(&self) -> Option<f64> {
+        match self.amdahl_parallel_fraction {
+            Some(p) if p < 1.0 => Some(1.0 / (1.0 - p)),
+            _ => None
+        }
+    }
+
+    /// Renders the sweep as a single JSON document.
+    ///
+    /// Speeds are in games per millisecond, of the measure the statistics use.
+    pub fn to_json(&self) -> String {
+        let points: Vec<String> = self.points.iter().map(|p| {
+            format!("    {{\"threads\": {}, \"speed\": {}, \"mean\": {}, \"speedup\": {}, \"efficiency\": {}, \"rank\": {}, \"score\": {}}}",
+                p.threads,
+                json::number(toms(p.report.measured_speed())),
+                json::number(toms(p.report.mean)),
+                json::number(p.speedup),
+                json::number(p.efficiency),
+                json::string(&p.report.rank_letter),
+                json::number(p.report.score)
+            )
+        }).collect();
+
+        let mut out = String::new();
+        out.push_str("{\n");
+        out.push_str("  \"points\": [\n");
+        out.push_str(&points.join(",\n"));
+        out.push_str("\n  ],\n");
+        out.push_str(&format!("  \"amdahl\": {{\"parallel_fraction\": {}, \"max_speedup\": {}}},\n",
+            json::optional(self.amdahl_parallel_fraction.map(json::number)),
+            json::optional(self.amdahl_max_speedup().map(json::number))
+        ));
+        out.push_str(&format!("  \"gustafson\": {{\"serial_fraction\": {}}},\n",
+            json::optional(self.gustafson_serial_fraction.map(json::number))
+        ));
+        out.push_str(&self.host.to_json_members());
+        out.push('\n');
+        out.push('}');
+        out
+    }
+
+}
+
+/// Displays the sweep as a table.
+impl fmt::Display for SweepReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Threads |      Speed |  Speedup | Efficiency | Rank")?;
+        for p in self.points.iter() {
+            writeln!(f, "{:7} | {:10.5} | {:8.3} | {:9.1}% | {}",
+                p.threads,
+                toms(p.report.measured_speed()),
+                p.speedup,
+                p.efficiency * 100.0,
+                p.report.rank_letter
+            )?;
+        }
+        writeln!(f, "---")?;
+        match (self.amdahl_parallel_fraction, self.amdahl_max_speedup()) {
+            (Some(p), Some(max)) => writeln!(f, "Amdahl: parallel fraction = {:.4}; max speedup = {:.2}", p, max)?,
+            (Some(p), None) => writeln!(f, "Amdahl: parallel fraction = {:.4}; max speedup = unlimited", p)?,
+            _ => writeln!(f, "Amdahl: needs more than one thread count")?
+        }
+        match self.gustafson_serial_fraction {
+            Some(s) => writeln!(f, "Gustafson: serial fraction = {:.4}", s)?,
+            None => writeln!(f, "Gustafson: needs more than one thread count")?
+        }
+        writeln!(f, "---")?;
+        write!(f, "{}", self.host)
+    }
+}
+
+/// Runs the benchmark for each thread count, with the other settings taken from `config`.
+///
+/// A single thread run is always included, since every speedup is relative to it.
+pub fn sweep(config: &BenchmarkConfig, threads: &[usize]) -> SweepReport {
+    let mut counts = threads.to_vec();
+    counts.push(1);
+    counts.sort();
+    counts.dedup();
+
+    let mut host = HostInfo::collect();
+
+    let mut reports = Vec::with_capacity(counts.len());
+    for &n in counts.iter() {
+        let mut c = config.clone();
+        c.threads = n;
+        eprintln!("threads = {}", n);
+        reports.push(benchmark::benchmark(&c));
+        // ends the progress line
+        eprintln!();
+    }
+
+    host.finish();
+
+    let base = reports[0].measured_speed();
+    let points: Vec<SweepPoint> = reports.into_iter().map(|r| {
+        let speedup = r.measured_speed() / base;
+        SweepPoint {
+            threads: r.config.threads,
+            speedup,
+            efficiency: speedup / r.config.threads as f64,
+            report: r,
+        }
+    }).collect();
+
+    let observations: Vec<(f64, f64)> = points.iter().map(|p| (p.threads as f64, p.speedup)).collect();
+
+    SweepReport {
+        amdahl_parallel_fraction: fit_amdahl(&observations),
+        gustafson_serial_fraction: fit_gustafson(&observations),
+        points,
+        host,
+    }
+}
+
+/// Fits `1 - 1/S = p (1 - 1/n)`, a line through the origin.
+fn fit_amdahl(observations: &[(f64, f64)]) -> Option<f64> {
+    fit_through_origin(observations.iter().map(|&(n, s)| (1.0 - 1.0 / n, 1.0 - 1.0 / s)))
+}
+
+/// Fits `S - 1 = (1 - s)(n - 1)`, a line through the origin.
+fn fit_gustafson(observations: &[(f64, f64)]) -> Option<f64> {
+    fit_through_origin(observations.iter().map(|&(n, s)| (n - 1.0, s - 1.0))).map(|slope| 1.0 - slope)
+}
+
+/// The least squares slope of `y = b x`; there is none when every `x` is 0.
+fn fit_through_origin<I: Iterator<Item=(f64, f64)>>(points: I) -> Option<f64> {
+    let mut xy = 0.0;
+    let mut xx = 0.0;
+    for (x, y) in points {
+        xy += x * y;
+        xx += x * x;
+    }
+    if xx == 0.0 {
+        None
+    } else {
+        Some(xy / xx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fits_recover_the_laws() {
+        let threads = [1.0, 2.0, 4.0, 8.0];
+        let amdahl: Vec<(f64, f64)> = threads.iter().map(|&n| (n, 1.0 / (0.2 + 0.8 / n))).collect();
+        assert!((fit_amdahl(&amdahl).unwrap() - 0.8).abs() < 1e-12);
+        let gustafson: Vec<(f64, f64)> = threads.iter().map(|&n| (n, n - 0.1 * (n - 1.0))).collect();
+        assert!((fit_gustafson(&gustafson).unwrap() - 0.1).abs() < 1e-12);
+        // a single thread alone fits nothing
+        assert_eq!(fit_amdahl(&[(1.0, 1.0)]), None);
+        assert_eq!(fit_gustafson(&[(1.0, 1.0)]), None);
+    }
+}
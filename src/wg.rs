@@ -1,11 +1,15 @@
 use std::fmt;
 use rand::Rng;
-use rand::ThreadRng;
+use rand::SeedableRng;
+use rand::StdRng;
 use std::cmp::Ordering;
+use std::collections::HashSet;
+
+use json;
 
 /// Value represents the Value the card.
-#[derive(Clone, Copy)]
-enum Value {
+#[derive(Clone, Copy, PartialEq)]
+pub enum Value {
 	Two,
 	Three,
 	Four,
@@ -18,7 +22,9 @@ enum Value {
 	Jack,
 	Queen,
 	King,
-	Ace
+	Ace,
+	/// Beats every other card; only in decks that ask for it, see `DeckSpec`.
+	Joker
 }
 
 /// Displays the Value as if they were a card.
@@ -38,6 +44,7 @@ impl fmt::Display for Value {
 			Value::Queen => "Queen",
 			Value::King => "King",
 			Value::Ace => "Ace",
+			Value::Joker => "Joker",
 		};
 		write!(f, "{}", name)
 	}
@@ -49,7 +56,7 @@ impl fmt::Display for Value {
 /// however this benefits debugging as each of the four variants
 /// of each card can be unique.
 #[derive(Clone, Copy)]
-enum Suit {
+pub enum Suit {
 	Clubs,
 	Hearts,
 	Diamonds,
@@ -73,15 +80,15 @@ impl fmt::Display for Suit {
 /// Each card should have a Value and Suit,
 /// although only Value is used to any effect.
 #[derive(Clone)]
-struct Card {
-	value: Value,
-	suit: Suit
+pub struct Card {
+	pub value: Value,
+	pub suit: Suit
 }
 
 impl Card {
 
 	/// To make a new card, provide Value and Suit.
-	fn new(value: Value, suit: Suit) -> Card {
+	pub fn new(value: Value, suit: Suit) -> Card {
 		Card {value: value, suit: suit}
 	}
 
@@ -94,8 +101,8 @@ impl Card {
 	/// used extensively in that setting.
 	///
 	/// Notice that the literal values are used:
-	/// 2..14; 1 is skipped
-	fn get_value(&self) -> i32 {
+	/// 2..14; 1 is skipped, and a Joker is 15
+	pub fn get_value(&self) -> i32 {
 		let v:i32 = match self.value {
 			Value::Two => 2,
 			Value::Three => 3,
@@ -110,6 +117,7 @@ impl Card {
 			Value::Queen => 12,
 			Value::King => 13,
 			Value::Ace => 14,
+			Value::Joker => 15,
 		};
 		return v;
 	}
@@ -119,9 +127,13 @@ impl Card {
 ///
 /// The format is {} of {}. For example,
 /// Ace of Spades or Three of Hearts.
+/// A Joker is only a Joker.
 impl fmt::Display for Card {
 	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-		write!(f, "{} of {}", self.value, self.suit)
+		match self.value {
+			Value::Joker => write!(f, "{}", self.value),
+			_ => write!(f, "{} of {}", self.value, self.suit)
+		}
 	}
 }
 
@@ -156,7 +168,7 @@ impl Ord for Card {
 ///
 /// You can think about Deck's as wrappers for the Vec's of cards.
 #[derive(Clone)]
-struct Deck(Vec<Card>);
+pub struct Deck(Vec<Card>);
 
 impl Deck {
 
@@ -167,7 +179,7 @@ impl Deck {
 	//
 	// This is a **static** method.
 	// Notice: it does not reference `self`.
-	fn new_fresh_deck() -> Deck {
+	pub fn new_fresh_deck() -> Deck {
 
 		let mut cards:Vec<Card> = Vec::with_capacity(52);
 		for suit in [Suit::Spades, Suit::Hearts, Suit::Diamonds, Suit::Clubs].iter() {
@@ -184,6 +196,30 @@ impl Deck {
 		Deck(cards)
 	}
 
+	/// Makes a deck to the given specification, unshuffled.
+	///
+	/// Each deck is laid out a suit at a time, the same as `new_fresh_deck`,
+	/// so the standard specification gives the same cards in the same order.
+	pub fn from_spec(spec: &DeckSpec) -> Deck {
+
+		let mut cards:Vec<Card> = Vec::with_capacity(spec.cards());
+		let rounds = spec.counts.iter().map(|&(_, count)| count).max().unwrap_or(0);
+		for _ in 0..spec.decks {
+			for round in 0..rounds {
+				for &(value, count) in spec.counts.iter() {
+					if round < count {
+						cards.push(Card::new(value, SUITS[round % SUITS.len()]));
+					}
+				}
+			}
+		}
+		// a black Joker and a red one, in turn
+		for i in 0..spec.jokers {
+			cards.push(Card::new(Value::Joker, SUITS[i % 2]));
+		}
+		Deck(cards)
+	}
+
 	/// Get a new empty deck.
 	///
 	/// This allocates the space for the cards, but does not
@@ -191,7 +227,7 @@ impl Deck {
 	///
 	/// This is a **static** method.
 	/// Notice: it does not reference `self`.
-	fn new() -> Deck {
+	pub fn new() -> Deck {
 		let cards:Vec<Card> = Vec::with_capacity(52);
 		Deck(cards)
 	}
@@ -201,7 +237,7 @@ impl Deck {
 	/// Instead of returning two new decks, only a single new deck is returned.
 	/// In this way, half the cards in kept in the first deck, and the other half is
 	/// returned anew.
-	fn split(&mut self) -> Deck {
+	pub fn split(&mut self) -> Deck {
 		let &mut Deck(ref mut cards) = self;
 		let length = cards.len();
 		let half = length / 2;
@@ -224,7 +260,7 @@ impl Deck {
 	///
 	/// Prior to 0.3.0, this used to create
 	/// a new ThreadRng generator everytime.
-	fn shuffle(&mut self, rng: &mut ThreadRng) {
+	pub fn shuffle<R: Rng>(&mut self, rng: &mut R) {
 		let &mut Deck(ref mut cards) = self;
 
 		// previously, this used a fresh ThreadRng
@@ -234,26 +270,47 @@ impl Deck {
 
 	}
 
+	/// Deals the whole deck out into `players` hands, one card at a time.
+	///
+	/// When the cards do not divide evenly, the first hands get one more.
+	pub fn deal(&mut self, players: usize) -> Vec<Deck> {
+		let &mut Deck(ref mut cards) = self;
+		let mut hands:Vec<Deck> = (0..players).map(|_| Deck(Vec::with_capacity(cards.len() / players + 1))).collect();
+		for (i, card) in cards.drain(..).enumerate() {
+			let Deck(ref mut hand) = hands[i % players];
+			hand.push(card);
+		}
+		hands
+	}
+
+	/// Appends the values of the cards, top first, to a game state.
+	fn state(&self, out: &mut Vec<u8>) {
+		let Deck(cards) = self;
+		for card in cards.iter() {
+			out.push(card.get_value() as u8);
+		}
+	}
+
 	/// Get the length of the deck of cards.
-	fn length(&mut self) -> usize {
+	pub fn length(&mut self) -> usize {
 		let &mut Deck(ref mut cards) = self;
 		return cards.len()
 	}
 
 	/// Returns if the deck has cards or not.
-	fn has_cards(&mut self) -> bool {
+	pub fn has_cards(&mut self) -> bool {
 		let &mut Deck(ref mut cards) = self;
 		cards.len() > 0
 	}
 
 	/// Gets the card at the top of the deck.
-	fn get_card(&mut self) -> Card {
+	pub fn get_card(&mut self) -> Card {
 		let &mut Deck(ref mut cards) = self;
 		cards[0].clone()
 	}
 
 	/// Removes card from the top of this deck and gives the card to the given deck.
-	fn give_card(&mut self, deck: &mut Deck) -> () {
+	pub fn give_card(&mut self, deck: &mut Deck) -> () {
 		let &mut Deck(ref mut cards) = self;
 		let &mut Deck(ref mut cards2) = deck;
 
@@ -269,7 +326,7 @@ impl Deck {
 	///
 	/// Prior to 0.3.0, this method had a bug.
 	/// It would call shuffle being providing the cards to the other deck.
-	fn give_cards(&mut self, deck: &mut Deck) -> () {
+	pub fn give_cards(&mut self, deck: &mut Deck) -> () {
 		for _ in 0..self.length() {
 			self.give_card(deck);
 		}
@@ -277,6 +334,13 @@ impl Deck {
 
 }
 
+/// The same as `Deck::new`.
+impl Default for Deck {
+	fn default() -> Deck {
+		Deck::new()
+	}
+}
+
 /// Displays a deck.
 impl fmt::Display for Deck {
 	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
@@ -296,20 +360,390 @@ impl fmt::Display for Deck {
 	}
 }
 
-/// Play the game of War.
-pub fn game(rng: &mut ThreadRng) {
+/// The suits, in the order a fresh deck is laid out in.
+const SUITS: [Suit; 4] = [Suit::Spades, Suit::Hearts, Suit::Diamonds, Suit::Clubs];
+
+/// The values of a standard deck, lowest first.
+const VALUES: [Value; 13] = [
+	Value::Two, Value::Three, Value::Four,
+	Value::Five, Value::Six, Value::Seven,
+	Value::Eight, Value::Nine, Value::Ten,
+	Value::Jack, Value::Queen, Value::King,
+	Value::Ace
+];
+
+/// What cards a game is played with.
+///
+/// One deck holds each value as many times as `counts` says, with the suits
+/// going round in turn; `decks` such decks are shuffled together, with `jokers` Jokers.
+/// More cards make longer games, and bigger hands to hold.
+#[derive(Clone, PartialEq)]
+pub struct DeckSpec {
+	/// How many cards of each value one deck holds.
+	pub counts: Vec<(Value, usize)>,
+	pub decks: usize,
+	/// Jokers in all, rather than in each deck.
+	pub jokers: usize
+}
+
+impl DeckSpec {
+
+	/// The standard deck of 52 cards.
+	pub fn standard() -> DeckSpec {
+		DeckSpec {
+			counts: VALUES.iter().map(|&v| (v, 4)).collect(),
+			decks: 1,
+			jokers: 0
+		}
+	}
+
+	/// The piquet deck of 32 cards, stripped of the Twos to Sixes.
+	pub fn piquet() -> DeckSpec {
+		DeckSpec {
+			counts: VALUES[5..].iter().map(|&v| (v, 4)).collect(),
+			decks: 1,
+			jokers: 0
+		}
+	}
+
+	/// The number of cards altogether.
+	pub fn cards(&self) -> usize {
+		self.counts.iter().fold(0, |a, &(_, count)| a + count) * self.decks + self.jokers
+	}
+
+	/// The name of one deck: `standard`, `piquet`, or its counts, such as `7:4,8:4,A:8`.
+	pub fn name(&self) -> String {
+		if self.counts == DeckSpec::standard().counts {
+			return "standard".to_string();
+		}
+		if self.counts == DeckSpec::piquet().counts {
+			return "piquet".to_string();
+		}
+		let counts: Vec<String> = self.counts.iter().map(|&(value, count)| {
+			format!("{}:{}", short_name(value), count)
+		}).collect();
+		counts.join(",")
+	}
+
+	/// Renders the specification as a JSON object.
+	pub fn to_json(&self) -> String {
+		format!("{{\"name\": {}, \"decks\": {}, \"jokers\": {}, \"cards\": {}}}",
+			json::string(&self.name()),
+			self.decks,
+			self.jokers,
+			self.cards()
+		)
+	}
+
+}
+
+/// Displays the specification as it is given on the command line,
+/// such as `standard`, or `2 x piquet + 2 jokers`.
+impl fmt::Display for DeckSpec {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		if self.decks != 1 {
+			write!(f, "{} x ", self.decks)?;
+		}
+		write!(f, "{}", self.name())?;
+		if self.jokers > 0 {
+			write!(f, " + {} jokers", self.jokers)?;
+		}
+		Ok(())
+	}
+}
+
+/// The short name of a value, as a deck's counts are given: `2` to `10`, `J`, `Q`, `K`, `A` and `Joker`.
+fn short_name(value: Value) -> &'static str {
+	match value {
+		Value::Two => "2",
+		Value::Three => "3",
+		Value::Four => "4",
+		Value::Five => "5",
+		Value::Six => "6",
+		Value::Seven => "7",
+		Value::Eight => "8",
+		Value::Nine => "9",
+		Value::Ten => "10",
+		Value::Jack => "J",
+		Value::Queen => "Q",
+		Value::King => "K",
+		Value::Ace => "A",
+		Value::Joker => "Joker",
+	}
+}
+
+/// Makes a generator from a seed.
+///
+/// The same seed always gives the same sequence of games,
+/// so a game played with it can be replayed.
+pub fn seeded_rng(seed: u64) -> StdRng {
+	let seed = [seed as usize, (seed >> 32) as usize];
+	StdRng::from_seed(&seed[..])
+}
+
+/// Which player won a game.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Winner {
+	Player1,
+	Player2,
+	/// Neither player won: a war could not be finished and `ShortWar` left it undecided,
+	/// or the game was stopped, at the turn cap or on a cycle; see `Ending`.
+	Draw
+}
+
+/// Displays the winner as a short name.
+impl fmt::Display for Winner {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let name = match *self {
+			Winner::Player1 => "P1",
+			Winner::Player2 => "P2",
+			Winner::Draw => "Draw",
+		};
+		write!(f, "{}", name)
+	}
+}
+
+/// How a game of War went.
+#[derive(Clone, Copy)]
+pub struct GameOutcome {
+	pub winner: Winner,
+	pub turns: u32,
+	/// Every war fought, including each war of a chain.
+	pub wars: u32,
+	/// The most wars fought back to back within a single turn.
+	pub deepest_war: u32,
+	/// Cards each player held when the game ended;
+	/// cards still on the table during a war are not counted.
+	pub player1_cards: usize,
+	pub player2_cards: usize,
+	/// The game ended because a player had too few cards for a war.
+	///
+	/// Who wins then depends on `Rules::short_war`.
+	pub war_exhaustion: bool,
+	/// Whether the game was played out, or stopped; a stopped game is a draw.
+	pub ending: Ending
+}
+
+/// How a game came to an end.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Ending {
+	/// Played out: a player took every card, or a war could not be finished.
+	Finished,
+	/// Stopped at `Rules::max_turns`.
+	TurnCap,
+	/// Stopped because the hands were as they had been at the start of an earlier turn;
+	/// see `Rules::detects_cycles`.
+	Cycle
+}
+
+/// Displays the ending as a short name.
+impl fmt::Display for Ending {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let name = match *self {
+			Ending::Finished => "finished",
+			Ending::TurnCap => "turn cap",
+			Ending::Cycle => "cycle",
+		};
+		write!(f, "{}", name)
+	}
+}
+
+/// What happens when a player has too few cards to finish a war.
+#[derive(Clone, Copy, PartialEq)]
+pub enum ShortWar {
+	/// The player who cannot finish loses; if neither can, the game is a draw.
+	Lose,
+	/// The player lays down what cards they have, turning up the last;
+	/// with no cards at all, the card they turned up before stands.
+	/// Only when neither player has a card left is the game a draw.
+	UseLast,
+	/// The game is a draw.
+	Draw
+}
+
+/// Displays the behaviour as it is given on the command line.
+impl fmt::Display for ShortWar {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let name = match *self {
+			ShortWar::Lose => "lose",
+			ShortWar::UseLast => "use-last",
+			ShortWar::Draw => "draw",
+		};
+		write!(f, "{}", name)
+	}
+}
+
+/// The order the winner of a battle or war takes the cards in.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Winnings {
+	Shuffled,
+	/// In the order they were laid down, player 1's card first each time.
+	///
+	/// Nothing is then left to chance after the deal,
+	/// and some games would repeat the same turns forever;
+	/// they are stopped, see `Rules::detects_cycles`.
+	InOrder
+}
+
+/// Displays the order as it is given on the command line.
+impl fmt::Display for Winnings {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		let name = match *self {
+			Winnings::Shuffled => "shuffled",
+			Winnings::InOrder => "in-order",
+		};
+		write!(f, "{}", name)
+	}
+}
+
+/// The rules a game of War is played by.
+///
+/// `Rules::new` gives the classic rules `game` has always played by;
+/// any field may be changed afterwards.
+#[derive(Clone)]
+pub struct Rules {
+	/// How many play; with more than two, see `multiplayer_game`.
+	pub players: usize,
+	/// The cards dealt.
+	pub deck: DeckSpec,
+	/// Cards each player lays face down in a war, before turning one up.
+	pub face_down: usize,
+	pub short_war: ShortWar,
+	pub winnings: Winnings,
+	/// Whether an Ace beats a King, or loses to a Two.
+	pub ace_high: bool,
+	/// Turns after which a game is stopped, as a draw.
+	pub max_turns: Option<u32>,
+	/// Whether a game is stopped, as a draw, when the hands repeat.
+	///
+	/// Only with `Winnings::InOrder`: nothing is then left to chance, so hands
+	/// that repeat will repeat forever. With shuffled winnings they need not.
+	/// Without `max_turns`, in-order games are checked whatever this says;
+	/// see `detects_cycles`.
+	pub detect_cycles: bool
+}
+
+impl Rules {
+
+	/// The classic rules: two players, a standard deck, three cards face down, a player short of cards for a war loses,
+	/// winnings are shuffled, and Aces are high; games are never stopped, and need not be.
+	pub fn new() -> Rules {
+		Rules {
+			players: 2,
+			deck: DeckSpec::standard(),
+			face_down: 3,
+			short_war: ShortWar::Lose,
+			winnings: Winnings::Shuffled,
+			ace_high: true,
+			max_turns: None,
+			detect_cycles: false
+		}
+	}
+
+	/// Renders the rules as a JSON object.
+	pub fn to_json(&self) -> String {
+		format!("{{\"players\": {}, \"deck\": {}, \"face_down\": {}, \"short_war\": {}, \"winnings\": {}, \"ace\": {}, \"max_turns\": {}, \"detect_cycles\": {}}}",
+			self.players,
+			self.deck.to_json(),
+			self.face_down,
+			json::string(&self.short_war.to_string()),
+			json::string(&self.winnings.to_string()),
+			json::string(if self.ace_high {"high"} else {"low"}),
+			json::optional(self.max_turns),
+			self.detects_cycles()
+		)
+	}
+
+	/// Whether games are checked for cycles: as asked with in-order winnings, and always
+	/// when in-order winnings have no turn cap, since nothing else would end such a game.
+	pub fn detects_cycles(&self) -> bool {
+		self.winnings == Winnings::InOrder && (self.detect_cycles || self.max_turns.is_none())
+	}
+
+	/// The value a card plays as under these rules.
+	pub fn rank(&self, card: &Card) -> i32 {
+		match card.value {
+			Value::Ace if !self.ace_high => 1,
+			_ => card.get_value()
+		}
+	}
+
+}
+
+/// The same as `Rules::new`.
+impl Default for Rules {
+	fn default() -> Rules {
+		Rules::new()
+	}
+}
+
+/// Displays the rules on a single line.
+impl fmt::Display for Rules {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "players = {}; deck = {}; face down = {}; short war = {}; winnings = {}; ace = {}",
+			self.players,
+			self.deck,
+			self.face_down, self.short_war, self.winnings, if self.ace_high {"high"} else {"low"})?;
+		if let Some(max) = self.max_turns {
+			write!(f, "; max turns = {}", max)?;
+		}
+		if self.detects_cycles() {
+			write!(f, "; detect cycles")?;
+		}
+		Ok(())
+	}
+}
+
+/// Play the game of War by the classic rules, and report how it went.
+///
+/// Any generator can be used; see `seeded_rng` for reproducible games.
+pub fn game<R: Rng>(rng: &mut R) -> GameOutcome {
+	game_with_rules(rng, &Rules::new())
+}
+
+/// Plays one game by the given rules, with however many players they name,
+/// for when only the work matters and not how the game went.
+pub fn play<R: Rng>(rng: &mut R, rules: &Rules) {
+	if rules.players > 2 {
+		multiplayer_game(rng, rules);
+	} else {
+		game_with_rules(rng, rules);
+	}
+}
+
+/// Play the game of War by the given rules, and report how it went.
+///
+/// This is the two player game, whatever `rules.players` says.
+/// A game that reaches `rules.max_turns`, or that cycles (see `Rules::detects_cycles`), is stopped as a draw.
+pub fn game_with_rules<R: Rng>(rng: &mut R, rules: &Rules) -> GameOutcome {
 
-	let mut player1 = Deck::new_fresh_deck();
+	let mut player1 = Deck::from_spec(&rules.deck);
 
 	player1.shuffle(rng);
 
 	let mut player2 = player1.split();
 
-	let mut turns = 0;
+	let mut turns:u32 = 0;
+	let mut wars:u32 = 0;
+	let mut deepest_war:u32 = 0;
+	let mut war_exhaustion = false;
+	let mut ending = Ending::Finished;
+
+	// the face down cards, and the one turned up
+	let needed = rules.face_down + 1;
 
 	let mut winner:Deck = Deck::new();
 
+	let cycles = rules.detects_cycles();
+	let mut seen:HashSet<Vec<u8>> = HashSet::new();
+
 	'base: while player1.has_cards() && player2.has_cards() {
+		if let Some(e) = stop(rules, turns, cycles, &mut seen, &[&player1, &player2]) {
+			info!(target: "game_events", "Stopped: {}", e);
+			ending = e;
+			break;
+		}
+
 		turns = turns + 1;
 
 		info!(target: "game_events", "Turn #{}", turns);
@@ -324,43 +758,62 @@ pub fn game(rng: &mut ThreadRng) {
 
 		info!(target: "game_events", "P1: `{}`; P2: `{}`", card1, card2);
 
-		if card1 == card2 {
+		if rules.rank(&card1) == rules.rank(&card2) {
 
-			let mut wars = 0;
+			let mut chain:u32 = 0;
 
-			'war: while {
+			while {
 
 				info!(target: "game_events", "P1: `{}` = P2: `{}`", card1, card2);
 
-				if player1.length() < 4 || player2.length() < 4 {
-					//info!(target: "game_events", ("Not enough cards for war!"));
-					break 'base;
+				if player1.length() < needed || player2.length() < needed {
+					let exhausted = match rules.short_war {
+						ShortWar::Lose | ShortWar::Draw => true,
+						ShortWar::UseLast => !player1.has_cards() && !player2.has_cards()
+					};
+					if exhausted {
+						//info!(target: "game_events", ("Not enough cards for war!"));
+						war_exhaustion = true;
+						break 'base;
+					}
 				}
 				wars = wars + 1;
-				info!(target: "game_events", "War #{}", wars);
+				chain += 1;
+				if chain > deepest_war {
+					deepest_war = chain;
+				}
+				info!(target: "game_events", "War #{}", chain);
+
+				// each player provides the face down cards to the winner,
+				// keeping back one to turn up
+				let down1 = rules.face_down.min(player1.length().saturating_sub(1));
+				let down2 = rules.face_down.min(player2.length().saturating_sub(1));
+				for i in 0..rules.face_down {
+					if i < down1 {
+						player1.give_card(&mut winner);
+					}
+					if i < down2 {
+						player2.give_card(&mut winner);
+					}
+				}
 
-				// each player provides 3 cards to the winner
-				for _ in 0..3 {
+				// get the top cards, and send them to the winner deck;
+				// a player with none left stands on the card turned up before
+				if player1.has_cards() {
+					card1 = player1.get_card();
 					player1.give_card(&mut winner);
+				}
+				if player2.has_cards() {
+					card2 = player2.get_card();
 					player2.give_card(&mut winner);
 				}
 
-				// get the top cards
-				card1 = player1.get_card();
-				card2 = player2.get_card();
-
-				// send the top cards to the winner deck
-				player1.give_card(&mut winner);
-				player2.give_card(&mut winner);
-
-				if card1 < card2 {
+				if rules.rank(&card1) < rules.rank(&card2) {
 					info!(target: "game_events", "P1: `{}` < P2: `{}`; W {}", card1, card2, winner.length());
-					winner.shuffle(rng);
-					winner.give_cards(&mut player2);
-				} else if card1 > card2 {
+					take_winnings(&mut winner, &mut player2, rules, rng);
+				} else if rules.rank(&card1) > rules.rank(&card2) {
 					info!(target: "game_events", "P1: `{}` > P2: `{}`; W {}", card1, card2, winner.length());
-					winner.shuffle(rng);
-					winner.give_cards(&mut player1);
+					take_winnings(&mut winner, &mut player1, rules, rng);
 				} else {
 					// perform another war
 					// the cards are equal
@@ -369,17 +822,15 @@ pub fn game(rng: &mut ThreadRng) {
 				// this must be the last line to emulate the do-while structure
 				// please, tell me how to convert this into a non-hack
 				// and it shall be done.
-				card1 == card2
+				rules.rank(&card1) == rules.rank(&card2)
 			} {}
 
-		} else if card1 < card2 {
+		} else if rules.rank(&card1) < rules.rank(&card2) {
 			info!(target: "game_events", "P1: `{}` < P2: `{}`; W {}", card1, card2, winner.length());
-			winner.shuffle(rng);
-			winner.give_cards(&mut player2);
-		} else if card1 > card2 {
+			take_winnings(&mut winner, &mut player2, rules, rng);
+		} else {
 			info!(target: "game_events", "P1: `{}` > P2: `{}`; W {}", card1, card2, winner.length());
-			winner.shuffle(rng);
-			winner.give_cards(&mut player1);
+			take_winnings(&mut winner, &mut player1, rules, rng);
 		}
 
 
@@ -387,4 +838,452 @@ pub fn game(rng: &mut ThreadRng) {
 
 	info!(target: "game_events", "Total turns: {}", turns);
 	info!(target: "game_events", "P1: {}; P2: {}; W {}", player1.length(), player2.length(), winner.length());
+
+	// whoever cannot continue loses: either out of cards,
+	// or, when a war is exhausted, short of the cards it needs
+	let (p1_out, p2_out) = if !war_exhaustion {
+		(!player1.has_cards(), !player2.has_cards())
+	} else if rules.short_war == ShortWar::Lose {
+		(player1.length() < needed, player2.length() < needed)
+	} else {
+		(true, true)
+	};
+
+	GameOutcome {
+		winner: match (p1_out, p2_out) {
+			(false, true) => Winner::Player1,
+			(true, false) => Winner::Player2,
+			_ => Winner::Draw
+		},
+		turns,
+		wars,
+		deepest_war,
+		player1_cards: player1.length(),
+		player2_cards: player2.length(),
+		war_exhaustion,
+		ending
+	}
+}
+
+/// How a game of War among more than two players went.
+#[derive(Clone)]
+pub struct MultiplayerOutcome {
+	/// The last player left holding cards, counting from 0;
+	/// `None` when the game was a draw because a war could not be finished,
+	/// or because it was stopped.
+	pub winner: Option<usize>,
+	/// The players in the order they ran out of cards, the first out first;
+	/// players out on the same turn are in player order.
+	pub eliminated: Vec<usize>,
+	pub turns: u32,
+	/// Every war fought, including each war of a chain.
+	pub wars: u32,
+	/// The most wars fought back to back within a single turn.
+	pub deepest_war: u32,
+	/// Cards each player held when the game ended.
+	pub cards: Vec<usize>,
+	/// The game ended because a war could not be finished.
+	pub war_exhaustion: bool,
+	pub ending: Ending
+}
+
+/// Play the game of War among `rules.players` players, and report how it went.
+///
+/// The deck is dealt out one card at a time. Each turn, every player with cards
+/// turns one up, and the highest takes them all. When the highest cards tie,
+/// only the tied players go to war, and a tie within the war goes on to another.
+/// A player is eliminated when they run out of cards.
+///
+/// A player short of cards for a war is handled by `rules.short_war`:
+/// with `Lose`, they forfeit their cards to the table and drop out of the war,
+/// and if every player in the war drops out the game is a draw;
+/// with `UseLast`, the game is a draw only when no player in the war has a card left;
+/// with `Draw`, the game is a draw.
+///
+/// As with two players, a game may be stopped as a draw by `rules.max_turns` or a cycle.
+pub fn multiplayer_game<R: Rng>(rng: &mut R, rules: &Rules) -> MultiplayerOutcome {
+
+	let mut deck = Deck::from_spec(&rules.deck);
+
+	deck.shuffle(rng);
+
+	let mut players = deck.deal(rules.players);
+
+	let mut turns:u32 = 0;
+	let mut wars:u32 = 0;
+	let mut deepest_war:u32 = 0;
+	let mut war_exhaustion = false;
+	let mut eliminated:Vec<usize> = Vec::with_capacity(players.len());
+	let mut ending = Ending::Finished;
+
+	let needed = rules.face_down + 1;
+
+	let mut winner:Deck = Deck::new();
+
+	let cycles = rules.detects_cycles();
+	let mut seen:HashSet<Vec<u8>> = HashSet::new();
+
+	'base: while players.iter_mut().map(|p| p.has_cards()).filter(|&h| h).count() > 1 {
+		let stopped = {
+			let hands:Vec<&Deck> = players.iter().collect();
+			stop(rules, turns, cycles, &mut seen, &hands)
+		};
+		if let Some(e) = stopped {
+			info!(target: "game_events", "Stopped: {}", e);
+			ending = e;
+			break;
+		}
+
+		turns += 1;
+
+		info!(target: "game_events", "Turn #{}", turns);
+
+		// every player with cards turns one up
+		let mut up:Vec<(usize, Card)> = Vec::with_capacity(players.len());
+		for (i, player) in players.iter_mut().enumerate() {
+			if player.has_cards() {
+				up.push((i, player.get_card()));
+				player.give_card(&mut winner);
+			}
+		}
+
+		let mut chain:u32 = 0;
+
+		loop {
+			let best = up.iter().map(|(_, c)| rules.rank(c)).max().unwrap_or(0);
+			let tied:Vec<(usize, Card)> = up.into_iter().filter(|(_, c)| rules.rank(c) == best).collect();
+
+			if tied.len() == 1 {
+				info!(target: "game_events", "P{}: `{}` wins; W {}", tied[0].0 + 1, tied[0].1, winner.length());
+				take_winnings(&mut winner, &mut players[tied[0].0], rules, rng);
+				break;
+			}
+
+			// the tied players go to war, those who can
+			let mut at_war:Vec<(usize, Card)> = Vec::with_capacity(tied.len());
+			for (i, card) in tied {
+				if players[i].length() < needed {
+					match rules.short_war {
+						ShortWar::Draw => {
+							war_exhaustion = true;
+							break 'base;
+						},
+						ShortWar::Lose => {
+							info!(target: "game_events", "P{}: not enough cards for war", i + 1);
+							players[i].give_cards(&mut winner);
+							continue;
+						},
+						ShortWar::UseLast => {}
+					}
+				}
+				at_war.push((i, card));
+			}
+
+			// with none left in the war, or none with a card left to turn up, it cannot be finished
+			if at_war.is_empty() || (at_war.len() > 1 && at_war.iter().all(|&(i, _)| !players[i].has_cards())) {
+				war_exhaustion = true;
+				break 'base;
+			}
+			if at_war.len() == 1 {
+				take_winnings(&mut winner, &mut players[at_war[0].0], rules, rng);
+				break;
+			}
+
+			wars += 1;
+			chain += 1;
+			if chain > deepest_war {
+				deepest_war = chain;
+			}
+			info!(target: "game_events", "War #{} among {} players", chain, at_war.len());
+
+			// each player provides the face down cards to the winner,
+			// keeping back one to turn up
+			let down:Vec<usize> = at_war.iter().map(|&(i, _)| {
+				rules.face_down.min(players[i].length().saturating_sub(1))
+			}).collect();
+			for k in 0..rules.face_down {
+				for (n, &(i, _)) in at_war.iter().enumerate() {
+					if k < down[n] {
+						players[i].give_card(&mut winner);
+					}
+				}
+			}
+
+			// a player with none left stands on the card turned up before
+			up = at_war.into_iter().map(|(i, card)| {
+				if players[i].has_cards() {
+					let c = players[i].get_card();
+					players[i].give_card(&mut winner);
+					(i, c)
+				} else {
+					(i, card)
+				}
+			}).collect();
+		}
+
+		for (i, player) in players.iter_mut().enumerate() {
+			if !player.has_cards() && !eliminated.contains(&i) {
+				info!(target: "game_events", "P{} is out", i + 1);
+				eliminated.push(i);
+			}
+		}
+	}
+
+	info!(target: "game_events", "Total turns: {}", turns);
+
+	let cards:Vec<usize> = players.iter_mut().map(|p| p.length()).collect();
+	let winner = if war_exhaustion || ending != Ending::Finished {
+		None
+	} else {
+		cards.iter().position(|&c| c > 0)
+	};
+
+	MultiplayerOutcome {
+		winner,
+		eliminated,
+		turns,
+		wars,
+		deepest_war,
+		cards,
+		war_exhaustion,
+		ending
+	}
+}
+
+/// Whether a game should be stopped before its next turn, and why.
+///
+/// `seen` holds the hands at the start of every turn so far; they are only
+/// recorded when looking for `cycles`.
+fn stop(rules: &Rules, turns: u32, cycles: bool, seen: &mut HashSet<Vec<u8>>, hands: &[&Deck]) -> Option<Ending> {
+	if let Some(max) = rules.max_turns {
+		if turns >= max {
+			return Some(Ending::TurnCap);
+		}
+	}
+	if cycles {
+		// a 0 marks where each hand ends
+		let mut state:Vec<u8> = Vec::with_capacity(rules.deck.cards() + hands.len());
+		for hand in hands.iter() {
+			hand.state(&mut state);
+			state.push(0);
+		}
+		if !seen.insert(state) {
+			return Some(Ending::Cycle);
+		}
+	}
+	None
+}
+
+/// Gives the cards on the table to the winner of a battle or war.
+fn take_winnings<R: Rng>(winnings: &mut Deck, deck: &mut Deck, rules: &Rules, rng: &mut R) {
+	if rules.winnings == Winnings::Shuffled {
+		winnings.shuffle(rng);
+	}
+	winnings.give_cards(deck);
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn seeded_shuffles_repeat() {
+		let shuffled = |seed: u64| {
+			let mut deck = Deck::new_fresh_deck();
+			deck.shuffle(&mut seeded_rng(seed));
+			deck.to_string()
+		};
+		assert_eq!(shuffled(7), shuffled(7));
+		assert!(shuffled(7) != shuffled(8));
+		assert!(shuffled(7) != Deck::new_fresh_deck().to_string());
+	}
+
+	#[test]
+	fn seeded_games_repeat() {
+		for seed in 0..20 {
+			let a = game(&mut seeded_rng(seed));
+			let b = game(&mut seeded_rng(seed));
+			assert!(a.winner == b.winner && a.turns == b.turns && a.wars == b.wars);
+		}
+	}
+
+	#[test]
+	fn classic_games_keep_every_card() {
+		for seed in 0..50 {
+			let outcome = game(&mut seeded_rng(seed));
+			match outcome.winner {
+				Winner::Player1 if !outcome.war_exhaustion => assert_eq!(outcome.player1_cards, 52),
+				Winner::Player2 if !outcome.war_exhaustion => assert_eq!(outcome.player2_cards, 52),
+				_ => assert!(outcome.player1_cards + outcome.player2_cards <= 52)
+			}
+		}
+	}
+
+	#[test]
+	fn aces_may_be_low() {
+		let mut rules = Rules::new();
+		let ace = Card::new(Value::Ace, Suit::Spades);
+		let two = Card::new(Value::Two, Suit::Spades);
+		assert!(rules.rank(&ace) > rules.rank(&two));
+		rules.ace_high = false;
+		assert!(rules.rank(&ace) < rules.rank(&two));
+	}
+
+	#[test]
+	fn dealing_shares_out_every_card() {
+		let mut deck = Deck::new_fresh_deck();
+		let mut hands = deck.deal(5);
+		let sizes: Vec<usize> = hands.iter_mut().map(|h| h.length()).collect();
+		assert_eq!(sizes, vec![11, 11, 10, 10, 10]);
+		assert!(!deck.has_cards());
+	}
+
+	#[test]
+	fn multiplayer_games_end_with_one_player_or_a_draw() {
+		let mut rules = Rules::new();
+		rules.players = 4;
+		for seed in 0..30 {
+			let outcome = multiplayer_game(&mut seeded_rng(seed), &rules);
+			let mut out = outcome.eliminated.clone();
+			out.sort();
+			out.dedup();
+			assert_eq!(out.len(), outcome.eliminated.len());
+			match outcome.winner {
+				Some(w) => {
+					assert_eq!(outcome.cards[w], 52);
+					assert_eq!(outcome.eliminated.len(), 3);
+					assert!(!outcome.eliminated.contains(&w));
+				},
+				None => assert!(outcome.war_exhaustion)
+			}
+		}
+	}
+
+	#[test]
+	fn short_wars_follow_the_rules() {
+		// with this many cards face down, wars often run short
+		let mut rules = Rules::new();
+		rules.face_down = 12;
+
+		rules.short_war = ShortWar::Draw;
+		let mut short = 0;
+		for seed in 0..100 {
+			let outcome = game_with_rules(&mut seeded_rng(seed), &rules);
+			if outcome.war_exhaustion {
+				short += 1;
+				assert!(outcome.winner == Winner::Draw);
+			} else {
+				assert!(outcome.winner != Winner::Draw);
+			}
+		}
+		assert!(short > 0);
+
+		// a player short of cards lays down what they have, so the winner takes every card
+		rules.short_war = ShortWar::UseLast;
+		for seed in 0..100 {
+			let outcome = game_with_rules(&mut seeded_rng(seed), &rules);
+			match outcome.winner {
+				Winner::Player1 => assert_eq!(outcome.player1_cards, 52),
+				Winner::Player2 => assert_eq!(outcome.player2_cards, 52),
+				Winner::Draw => assert!(outcome.war_exhaustion && outcome.player1_cards + outcome.player2_cards == 0)
+			}
+		}
+
+		rules.players = 3;
+		for &short_war in [ShortWar::Draw, ShortWar::UseLast].iter() {
+			rules.short_war = short_war;
+			for seed in 0..50 {
+				let outcome = multiplayer_game(&mut seeded_rng(seed), &rules);
+				match outcome.winner {
+					Some(w) => assert_eq!(outcome.cards[w], 52),
+					None => assert!(outcome.war_exhaustion)
+				}
+			}
+		}
+	}
+
+	fn cards(deck: &Deck) -> Vec<String> {
+		deck.0.iter().map(|c| c.to_string()).collect()
+	}
+
+	#[test]
+	fn standard_spec_is_the_fresh_deck() {
+		let spec = DeckSpec::standard();
+		assert_eq!(spec.cards(), 52);
+		assert_eq!(spec.to_string(), "standard");
+		assert_eq!(cards(&Deck::from_spec(&spec)), cards(&Deck::new_fresh_deck()));
+	}
+
+	#[test]
+	fn deck_specs_count_their_cards() {
+		let piquet = DeckSpec::piquet();
+		assert_eq!(piquet.cards(), 32);
+		assert_eq!(Deck::from_spec(&piquet).length(), 32);
+
+		let spec = DeckSpec {decks: 2, jokers: 3, ..DeckSpec::standard()};
+		assert_eq!(spec.to_string(), "2 x standard + 3 jokers");
+		let deck = Deck::from_spec(&spec);
+		assert_eq!(deck.0.len(), 107);
+		assert_eq!(deck.0.iter().filter(|c| c.get_value() == 15).count(), 3);
+
+		let custom = DeckSpec {counts: vec![(Value::King, 2), (Value::Ace, 8)], decks: 1, jokers: 0};
+		assert_eq!(custom.name(), "K:2,A:8");
+		assert_eq!(custom.cards(), 10);
+		// suits go round in turn
+		let deck = Deck::from_spec(&custom);
+		assert_eq!(deck.0.iter().filter(|c| c.get_value() == 14).count(), 8);
+		assert_eq!(cards(&deck)[..3].to_vec(), vec!["King of Spades", "Ace of Spades", "King of Hearts"]);
+	}
+
+	#[test]
+	fn in_order_games_are_stopped() {
+		let mut rules = Rules::new();
+		rules.winnings = Winnings::InOrder;
+		rules.detect_cycles = true;
+		let mut cycles = 0;
+		for seed in 0..200 {
+			let outcome = game_with_rules(&mut seeded_rng(seed), &rules);
+			if outcome.ending == Ending::Cycle {
+				cycles += 1;
+				assert!(outcome.winner == Winner::Draw);
+			}
+		}
+		assert!(cycles > 0);
+
+		rules.detect_cycles = false;
+		rules.max_turns = Some(25);
+		for seed in 0..50 {
+			let outcome = game_with_rules(&mut seeded_rng(seed), &rules);
+			assert!(outcome.turns <= 25);
+			if outcome.ending == Ending::TurnCap {
+				assert_eq!(outcome.turns, 25);
+				assert!(outcome.winner == Winner::Draw);
+			}
+		}
+	}
+
+	#[test]
+	fn in_order_games_end_without_a_guard() {
+		let mut rules = Rules::new();
+		rules.winnings = Winnings::InOrder;
+		assert!(rules.detects_cycles());
+		for &players in [2, 3].iter() {
+			rules.players = players;
+			for seed in 0..200 {
+				play(&mut seeded_rng(seed), &rules);
+			}
+		}
+		// a turn cap is guard enough, unless cycles are asked for too
+		rules.max_turns = Some(1000);
+		assert!(!rules.detects_cycles());
+		rules.detect_cycles = true;
+		assert!(rules.detects_cycles());
+	}
+
+	#[test]
+	fn classic_games_are_played_out() {
+		for seed in 0..50 {
+			assert!(game(&mut seeded_rng(seed)).ending == Ending::Finished);
+		}
+	}
 }
@@ -0,0 +1,113 @@
+//! The worker threads that play the WarGame.
+//!
+//! Each worker counts its own completed games in an atomic counter,
+//! and checks a shared atomic flag to know when to stop.
+//! The monitor reads the counters whenever it likes; nothing is queued,
+//! so the cost of reporting a game stays the same however long the run.
+
+use std::panic;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+
+use std::thread;
+use std::thread::JoinHandle;
+
+use rand;
+use rand::Rng;
+
+use wg;
+use wg::Rules;
+
+use affinity;
+
+/// A completion counter, alone on its cache line.
+///
+/// Workers write to their counters constantly; padding them apart
+/// stops one worker's writes from evicting another's line.
+/// 128 bytes covers the adjacent line prefetch of recent x86 processors.
+#[repr(align(128))]
+struct Counter(AtomicU64);
+
+struct Shared {
+    counters: Vec<Counter>,
+    stop: AtomicBool,
+}
+
+/// A running set of worker threads.
+pub struct Workers {
+    shared: Arc<Shared>,
+    handles: Vec<JoinHandle<Option<usize>>>,
+}
+
+impl Workers {
+
+    /// Starts `threads` workers, each playing games until stopped.
+    ///
+    /// Each worker gets its own generator; when seeded,
+    /// worker `i` uses `seed + i` so its games can be replayed on their own.
+    /// When given CPUs, worker `i` pins itself to `cpus[i]` before its first game.
+    pub fn start(threads: usize, seed: Option<u64>, cpus: Option<Vec<usize>>, rules: &Rules) -> Workers {
+        let shared = Arc::new(Shared {
+            counters: (0..threads).map(|_| Counter(AtomicU64::new(0))).collect(),
+            stop: AtomicBool::new(false),
+        });
+
+        let mut handles = Vec::with_capacity(threads);
+        for i in 0..threads {
+            let shared = shared.clone();
+            let cpu = cpus.as_ref().and_then(|c| c.get(i).cloned());
+            let rules = rules.clone();
+            handles.push(thread::spawn(move || {
+                let pinned = cpu.and_then(affinity::pin);
+                match seed {
+                    Some(seed) => play(&mut wg::seeded_rng(seed.wrapping_add(i as u64)), &rules, &shared, i),
+                    None => play(&mut rand::thread_rng(), &rules, &shared, i)
+                }
+                pinned
+            }));
+        }
+
+        Workers {shared, handles}
+    }
+
+    /// Games completed so far, by all workers.
+    pub fn games(&self) -> u64 {
+        self.shared.counters.iter().fold(0, |total, c| total + c.0.load(Ordering::Relaxed))
+    }
+
+    /// Games completed so far, by each worker.
+    pub fn games_per_thread(&self) -> Vec<u64> {
+        self.shared.counters.iter().map(|c| c.0.load(Ordering::Relaxed)).collect()
+    }
+
+    /// Signals every worker to stop after its current game, and waits for them.
+    ///
+    /// Returns the CPU each worker was pinned to, if it was.
+    /// If a worker panicked, its panic is resumed once every worker has been joined,
+    /// rather than the run being reported with a worker missing.
+    pub fn stop(self) -> Vec<Option<usize>> {
+        self.shared.stop.store(true, Ordering::Relaxed);
+        let results: Vec<thread::Result<Option<usize>>> = self.handles.into_iter().map(|h| h.join()).collect();
+
+        let mut placement = Vec::with_capacity(results.len());
+        for result in results {
+            match result {
+                Ok(pinned) => placement.push(pinned),
+                Err(payload) => panic::resume_unwind(payload)
+            }
+        }
+        placement
+    }
+
+}
+
+/// The worker's tight loop.
+fn play<R: Rng>(rng: &mut R, rules: &Rules, shared: &Shared, i: usize) {
+    let counter = &shared.counters[i].0;
+    while !shared.stop.load(Ordering::Relaxed) {
+        // the entire point of this: run the wargame
+        wg::play(rng, rules);
+        // only this worker writes its counter
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+}